@@ -24,6 +24,29 @@ fn benchmark_native(c: &mut Criterion) {
     c.bench_function("Acas Spear Rotation", |b| b.iter(|| test_native(input_slice,&[0,0,0,0],width,height)));
 }
 
+// Compares the runtime channel count with the const generic one for RGBA
+fn benchmark_const_channels(c: &mut Criterion) {
+    let input_image = Reader::open("benches/input.png").unwrap().decode().unwrap().into_rgba8();
+    let (width,height) = input_image.dimensions();
+    let input_buffer = input_image.into_vec();
+    let input_slice = input_buffer.as_slice();
+
+    c.bench_function("Acas Spear Rotation (runtime channels)", |b| b.iter(|| stitch::fast_rotate(input_slice, &[0,0,0,0], 4, width as usize, height as usize, consts::PI/4.0).unwrap()));
+    c.bench_function("Acas Spear Rotation (const channels)", |b| b.iter(|| stitch::fast_rotate_n(input_slice, &[0,0,0,0], width as usize, height as usize, consts::PI/4.0).unwrap()));
+}
+
+// Compares the fancy levels, each one upscales twice as far as the last before rotating
+fn benchmark_fancy_levels(c: &mut Criterion) {
+    let input_image = Reader::open("benches/input.png").unwrap().decode().unwrap().into_rgba8();
+    let (width,height) = input_image.dimensions();
+    let input_buffer = input_image.into_vec();
+    let input_slice = input_buffer.as_slice();
+
+    for (name, quality) in [("2x", stitch::StitchingQuality::Fancy2x), ("4x", stitch::StitchingQuality::Fancy4x), ("8x", stitch::StitchingQuality::Fancy8x)] {
+        c.bench_function(&format!("Acas Spear Rotation (fancy {})", name), |b| b.iter(|| stitch::rotate_with_quality(input_slice, &[0,0,0,0], 4, width as usize, height as usize, consts::PI/4.0, &quality).unwrap()));
+    }
+}
+
 fn benchmark_foreign(c: &mut Criterion) {
     let img = image::open("benches/input.png").unwrap();
     let width = img.width() as usize;
@@ -38,9 +61,84 @@ fn benchmark_foreign(c: &mut Criterion) {
     c.bench_function("Rotsprite Spear Rotation", |b| b.iter(|| test_rotsprite(&pixels,&unfound_color,width,height)));
 }
 
+// Compares rendering 36 frames of a spinning sprite with rotate_sequence against calling fancy_rotate for every frame
+fn benchmark_sequence(c: &mut Criterion) {
+    let input_image = Reader::open("benches/input.png").unwrap().decode().unwrap().into_rgba8();
+    let (width,height) = input_image.dimensions();
+    let input_buffer = input_image.into_vec();
+    let input_slice = input_buffer.as_slice();
+
+    let angles: Vec<f64> = (0..36).map(|frame| frame as f64*consts::PI/18.0).collect();
+
+    c.bench_function("Acas 36 Frame Rotation (fancy_rotate loop)", |b| b.iter(|| {
+        for angle in angles.iter() {
+            stitch::fancy_rotate(input_slice, &[0,0,0,0], 4, width as usize, height as usize, *angle).unwrap();
+        }
+    }));
+    c.bench_function("Acas 36 Frame Rotation (rotate_sequence)", |b| b.iter(|| stitch::rotate_sequence(input_slice, &[0,0,0,0], 4, width as usize, height as usize, &angles).unwrap()));
+}
+
+// Compares rotating every frame with fast_rotate against reusing one buffer with rotate_into
+fn benchmark_rotate_into(c: &mut Criterion) {
+    let input_image = Reader::open("benches/input.png").unwrap().decode().unwrap().into_rgba8();
+    let (width,height) = input_image.dimensions();
+    let input_buffer = input_image.into_vec();
+    let input_slice = input_buffer.as_slice();
+
+    let angles: Vec<f64> = (0..36).map(|frame| frame as f64*consts::PI/18.0).collect();
+
+    c.bench_function("Acas 36 Frame Rotation (fast_rotate loop)", |b| b.iter(|| {
+        for angle in angles.iter() {
+            stitch::fast_rotate(input_slice, &[0,0,0,0], 4, width as usize, height as usize, *angle).unwrap();
+        }
+    }));
+
+    let mut out = Vec::new();
+    c.bench_function("Acas 36 Frame Rotation (rotate_into loop)", |b| b.iter(|| {
+        for angle in angles.iter() {
+            stitch::rotate_into(input_slice, &[0,0,0,0], 4, width as usize, height as usize, *angle, &mut out).unwrap();
+        }
+    }));
+}
+
+// Compares the general rotation with one channel against the single channel specialisation, on a grayscale copy of the input
+fn benchmark_gray(c: &mut Criterion) {
+    let input_image = Reader::open("benches/input.png").unwrap().decode().unwrap().into_luma8();
+    let (width,height) = input_image.dimensions();
+    let input_buffer = input_image.into_vec();
+    let input_slice = input_buffer.as_slice();
+
+    c.bench_function("Acas Grayscale Rotation (fast_rotate)", |b| b.iter(|| stitch::fast_rotate(input_slice, &[0], 1, width as usize, height as usize, consts::PI/4.0).unwrap()));
+    c.bench_function("Acas Grayscale Rotation (rotate_gray)", |b| b.iter(|| stitch::rotate_gray(input_slice, width as usize, height as usize, consts::PI/4.0, stitch::EdgeMode::Fill(&[0])).unwrap()));
+}
+
+// Compares the serial and multithreaded rotation on an 8x upscaled copy of the input
+#[cfg(feature = "parallel")]
+fn benchmark_parallel(c: &mut Criterion) {
+    let input_image = Reader::open("benches/input.png").unwrap().decode().unwrap().into_rgba8();
+    let (width,height) = input_image.dimensions();
+    let input_buffer = input_image.into_vec();
+
+    let image2x = stitch::upscale(input_buffer.as_slice(), width as usize, height as usize, 4).unwrap();
+    let image4x = stitch::upscale(image2x.2.as_slice(), image2x.0, image2x.1, 4).unwrap();
+    let image8x = stitch::upscale(image4x.2.as_slice(), image4x.0, image4x.1, 4).unwrap();
+    let large_slice = image8x.2.as_slice();
+
+    c.bench_function("Acas Large Rotation (serial)", |b| b.iter(|| stitch::fast_rotate(large_slice, &[0,0,0,0], 4, image8x.0, image8x.1, consts::PI/4.0).unwrap()));
+    c.bench_function("Acas Large Rotation (parallel)", |b| b.iter(|| stitch::fast_rotate_parallel(large_slice, &[0,0,0,0], 4, image8x.0, image8x.1, consts::PI/4.0).unwrap()));
+}
+
+#[cfg(not(feature = "parallel"))]
+criterion_group!{
+    name = benches;
+    config = Criterion::default().sample_size(50);
+    targets = benchmark_native, benchmark_const_channels, benchmark_fancy_levels, benchmark_foreign, benchmark_sequence, benchmark_rotate_into, benchmark_gray
+}
+
+#[cfg(feature = "parallel")]
 criterion_group!{
     name = benches;
     config = Criterion::default().sample_size(50);
-    targets = benchmark_native, benchmark_foreign
+    targets = benchmark_native, benchmark_const_channels, benchmark_fancy_levels, benchmark_foreign, benchmark_sequence, benchmark_rotate_into, benchmark_gray, benchmark_parallel
 }
 criterion_main!(benches);
\ No newline at end of file
@@ -0,0 +1,35 @@
+/*
+ Times stitching the sword of the stitching example onto one of its frames
+*/
+use criterion::{criterion_group, criterion_main, Criterion};
+use acas::stitch;
+use image::io::Reader;
+
+fn load(path: &str) -> (Vec<u8>, (usize,usize)) {
+    let image = Reader::open(path).unwrap().decode().unwrap().into_rgba8();
+    let (width,height) = image.dimensions();
+
+    (image.into_vec(), (width as usize, height as usize))
+}
+
+// The anchors and angles are the ones examples/stitching/assets/rig.json gives the second frame
+fn benchmark_stitch(c: &mut Criterion) {
+    let (frame, frame_dimensions) = load("examples/stitching/assets/frame2.png");
+    let (sword, sword_dimensions) = load("examples/stitching/assets/sword.png");
+
+    let joint = stitch::Joint::new((5,34), 13f64.to_radians(), (24,12), (-27f64).to_radians(), stitch::StitchingOrder::AppendageOnTop);
+    let appendage = stitch::Appendage { buf: &sword, dimensions: sword_dimensions, joint };
+
+    for (name, quality) in [("fast", stitch::StitchingQuality::Fast), ("fancy", stitch::StitchingQuality::Fancy)] {
+        c.bench_function(&format!("Acas Sword Stitch ({})", name), |b| b.iter(|| stitch::stitch_appendage(
+            &frame, frame_dimensions, &appendage, &[0,0,0,0], 4, quality
+        ).unwrap()));
+    }
+}
+
+criterion_group!{
+    name = benches;
+    config = Criterion::default().sample_size(50);
+    targets = benchmark_stitch
+}
+criterion_main!(benches);
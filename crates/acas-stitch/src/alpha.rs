@@ -0,0 +1,140 @@
+use crate::{buffer_len, check_buffer, AlphaChannel, StitchableType, StitchingResult};
+
+/// Multiplies the color channels of every pixel by its alpha,
+/// converting a straight alpha image to premultiplied alpha
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - alpha_channel - Which channel holds the alpha value, with AlphaChannel::Opaque the image is returned as is
+///
+/// Precision is lost for low alpha values, so convert once and keep working on the premultiplied image
+pub fn premultiply<T>(buf: &[T], width: usize, height: usize, channels: usize, alpha_channel: AlphaChannel) -> StitchingResult<T>
+where T: StitchableType
+{
+    convert_alpha(buf, width, height, channels, alpha_channel, |color, alpha| color*alpha)
+}
+
+/// Divides the color channels of every pixel by its alpha, the inverse of premultiply()
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - alpha_channel - Which channel holds the alpha value, with AlphaChannel::Opaque the image is returned as is
+///
+/// Pixels with an alpha of 0 have no color left to recover, so their color channels are set to 0
+pub fn unpremultiply<T>(buf: &[T], width: usize, height: usize, channels: usize, alpha_channel: AlphaChannel) -> StitchingResult<T>
+where T: StitchableType
+{
+    convert_alpha(buf, width, height, channels, alpha_channel, |color, alpha| if alpha > 0.0 { f64::min(color/alpha, 1.0) } else { 0.0 })
+}
+
+/// Appends a fully opaque alpha channel to every pixel, e.g. turning RGB into RGBA
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel, the result has channels+1
+pub fn add_opaque_alpha<T>(buf: &[T], width: usize, height: usize, channels: usize) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(width, height, channels+1)?);
+
+    for pixel in buf.chunks(channels) {
+        result_buffer.extend_from_slice(pixel);
+        result_buffer.push(T::maxvalue());
+    }
+
+    Ok((width,height,result_buffer))
+}
+
+/// Appends the values of a separate single channel mask to every pixel as its alpha channel, e.g. turning RGB and a mask into RGBA
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel, the result has channels+1
+/// - mask - The alpha value of each pixel, must have the same dimensions as the image
+pub fn add_alpha_mask<T>(buf: &[T], width: usize, height: usize, channels: usize, mask: &[T]) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+    check_buffer(mask, 1, width, height)?;
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(width, height, channels+1)?);
+
+    for (pixel, alpha) in buf.chunks(channels).zip(mask.iter()) {
+        result_buffer.extend_from_slice(pixel);
+        result_buffer.push(*alpha);
+    }
+
+    Ok((width,height,result_buffer))
+}
+
+/// Applies convert(color, alpha) to every color channel, with both values normalised to 0.0..=1.0
+fn convert_alpha<T>(buf: &[T], width: usize, height: usize, channels: usize, alpha_channel: AlphaChannel, convert: impl Fn(f64, f64) -> f64) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+
+    let alpha_index = match alpha_channel.index(channels)? {
+        Some(index) => index,
+        None => return Ok((width,height,buf.to_vec()))
+    };
+
+    let max = T::maxvalue().to_f64();
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buf.len());
+
+    for pixel in buf.chunks(channels) {
+        let alpha = pixel[alpha_index].to_f64()/max;
+
+        for (c, value) in pixel.iter().enumerate() {
+            if c == alpha_index {
+                result_buffer.push(*value);
+            } else {
+                result_buffer.push(T::from_f64(convert(value.to_f64()/max, alpha)*max));
+            }
+        }
+    }
+
+    Ok((width,height,result_buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn premultiply_round_trips_within_rounding() {
+        let buf: Vec<u8> = [0u8, 1, 17, 128, 200, 255].iter()
+            .flat_map(|alpha| [0u8, 33, 200, 255].map(|color| [color, 255-color, color/2, *alpha]))
+            .flatten()
+            .collect();
+        let pixels = buf.len()/4;
+
+        let premultiplied = premultiply(&buf, pixels, 1, 4, AlphaChannel::Last).unwrap().2;
+        let restored = unpremultiply(&premultiplied, pixels, 1, 4, AlphaChannel::Last).unwrap().2;
+
+        for (original, restored) in buf.chunks(4).zip(restored.chunks(4)) {
+            assert_eq!(original[3], restored[3]);
+            if original[3] == 0 {
+                assert_eq!(restored[0..3], [0, 0, 0]);
+                continue
+            }
+
+            // Premultiplying rounds to a step of 255/alpha in the original range
+            let tolerance = (255.0/original[3] as f64/2.0).ceil() as u8;
+            for c in 0..3 {
+                assert!(original[c].abs_diff(restored[c]) <= tolerance, "{:?} came back as {:?}", original, restored);
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::Write;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use thiserror::Error;
+
+use crate::{pad_frames, Image, ParsingError};
+
+#[derive(Error, Debug)]
+/// The Types of Errors that can occur when exporting an animation
+pub enum AnimationError {
+    #[error("Invalid frames: {0}")]
+    /// The frames passed could not be combined into an animation, e.g. they are not all 4 channel images
+    Frames(#[from] ParsingError),
+    #[error("Could not encode animation: {0}")]
+    /// The image crate failed to encode or write the animation
+    Encoding(#[from] image::ImageError)
+}
+
+/// Encodes 8 bit RGBA frames, e.g. the ones from rotate_sequence(), as a looping animated GIF
+///
+/// # Arguments
+///
+/// - frames - The frames in the order they are played, they are padded to a common size with pad_frames() so the animation doesn't jitter
+/// - empty - The padding is filled with this value
+/// - delay_ms - How long each frame is shown for, in milliseconds
+/// - writer - Where the GIF is written to, e.g. a File
+///
+/// GIF only supports fully transparent or fully opaque pixels, so partially transparent pixels lose their alpha.
+/// Returns AnimationError::Frames with ParsingError::ChannelMismatch if the frames do not have 4 channels
+pub fn encode_gif<W: Write>(frames: &[Image<u8>], empty: &[u8], delay_ms: u32, writer: W) -> Result<(), AnimationError> {
+    let padded = pad_frames(frames, empty)?;
+    if padded[0].channels() != 4 {
+        return Err(ParsingError::ChannelMismatch.into())
+    }
+
+    let delay = Delay::from_numer_denom_ms(delay_ms, 1);
+    let mut gif_frames = Vec::with_capacity(padded.len());
+
+    for frame in padded {
+        let buffer: RgbaImage = frame.into_image_buffer()?;
+        gif_frames.push(Frame::from_parts(buffer, 0, 0, delay));
+    }
+
+    let mut encoder = GifEncoder::new(writer);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(gif_frames)?;
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use crate::{buffer_len, check_empty, filled_canvas, AtlasResult, Image, ParsingError};
+
+/// Lays frames out in a grid to make a sprite sheet
+///
+/// # Arguments
+///
+/// - frames - The frames, placed left to right and then top to bottom. They may have different sizes but must have the same no. of channels
+/// - columns - No. of frames per row, 0 is treated as 1
+/// - padding - Empty pixels left between neighbouring frames
+/// - empty - Gaps are filled with this value
+///
+/// Each column is as wide as its widest frame and each row as tall as its tallest frame, frames sit in the top left of their cell.
+/// Returns the sheet along with the rectangle (x, y, width, height) of each frame in it, in the same order as frames
+pub fn pack_grid<T: Clone>(frames: &[Image<T>], columns: usize, padding: usize, empty: &[T]) -> AtlasResult<T> {
+    let channels = check_frames(frames, empty)?;
+    let columns = columns.max(1);
+
+    let mut column_widths = vec![0; usize::min(columns, frames.len())];
+    let mut row_heights = vec![0; (frames.len()+columns-1)/columns];
+
+    for (index, frame) in frames.iter().enumerate() {
+        column_widths[index%columns] = usize::max(column_widths[index%columns], frame.width());
+        row_heights[index/columns] = usize::max(row_heights[index/columns], frame.height());
+    }
+
+    let offsets = |lengths: &[usize]| -> Result<(Vec<usize>, usize), ParsingError> {
+        let mut offsets = Vec::with_capacity(lengths.len());
+        let mut total: usize = 0;
+
+        for (index, length) in lengths.iter().enumerate() {
+            if index > 0 {
+                total = total.checked_add(padding).ok_or(ParsingError::DimensionsTooLarge)?;
+            }
+            offsets.push(total);
+            total = total.checked_add(*length).ok_or(ParsingError::DimensionsTooLarge)?;
+        }
+
+        Ok((offsets, total))
+    };
+
+    let (column_offsets, width) = offsets(&column_widths)?;
+    let (row_offsets, height) = offsets(&row_heights)?;
+
+    let rects: Vec<(usize,usize,usize,usize)> = frames.iter().enumerate()
+        .map(|(index, frame)| (column_offsets[index%columns], row_offsets[index/columns], frame.width(), frame.height()))
+        .collect();
+
+    Ok((draw_frames(frames, &rects, (width, height), channels, empty)?, rects))
+}
+
+/// Packs frames into rows ("shelves") to make a compact sprite atlas, pairs well with trim()
+///
+/// # Arguments
+///
+/// - frames - The frames, which may have different sizes but must have the same no. of channels
+/// - max_width - The widest the atlas may be, a new row is started whenever the next frame doesn't fit
+/// - padding - Empty pixels left between neighbouring frames
+/// - empty - Gaps are filled with this value
+///
+/// The frames are placed tallest first to waste less space, but the rectangles (x, y, width, height) are returned in the same order as frames.
+/// Returns ParsingError::FrameTooWide if a frame is wider than max_width
+pub fn pack_atlas<T: Clone>(frames: &[Image<T>], max_width: usize, padding: usize, empty: &[T]) -> AtlasResult<T> {
+    let channels = check_frames(frames, empty)?;
+    if frames.iter().any(|frame| frame.width() > max_width) {
+        return Err(ParsingError::FrameTooWide)
+    }
+
+    let mut order: Vec<usize> = (0..frames.len()).collect();
+    order.sort_by_key(|index| std::cmp::Reverse(frames[*index].height()));
+
+    let add = |a: usize, b: usize| a.checked_add(b).ok_or(ParsingError::DimensionsTooLarge);
+
+    let mut rects = vec![(0, 0, 0, 0); frames.len()];
+    let (mut x, mut y, mut row_height, mut width) = (0, 0, 0, 0);
+
+    for index in order {
+        let frame = &frames[index];
+
+        // Padding only goes between frames, so it is fine for it to stick past max_width
+        if x > 0 && add(x, frame.width())? > max_width {
+            y = add(y, add(row_height, padding)?)?;
+            x = 0;
+            row_height = 0;
+        }
+
+        rects[index] = (x, y, frame.width(), frame.height());
+        width = usize::max(width, x + frame.width());
+        row_height = usize::max(row_height, frame.height());
+        x = add(x, add(frame.width(), padding)?)?;
+    }
+
+    Ok((draw_frames(frames, &rects, (width, add(y, row_height)?), channels, empty)?, rects))
+}
+
+/// Pads frames of different sizes, like the ones returned by rotate_sequence(), to the size of the largest one
+/// so they can be played back as an animation without jittering
+///
+/// # Arguments
+///
+/// - frames - The frames, which may have different sizes but must have the same no. of channels
+/// - empty - The padding is filled with this value
+///
+/// Each frame is centered in its padded copy, which keeps the center of a rotating sprite in place
+pub fn pad_frames<T: Clone>(frames: &[Image<T>], empty: &[T]) -> Result<Vec<Image<T>>, ParsingError> {
+    let channels = check_frames(frames, empty)?;
+
+    let width = frames.iter().map(|frame| frame.width()).max().unwrap_or(0);
+    let height = frames.iter().map(|frame| frame.height()).max().unwrap_or(0);
+
+    frames.iter().map(|frame| {
+        let rect = ((width-frame.width())/2, (height-frame.height())/2, frame.width(), frame.height());
+        draw_frames(std::slice::from_ref(frame), &[rect], (width, height), channels, empty)
+    }).collect()
+}
+
+/// Checks that there is at least one frame and that all of them and the empty pixel have the same no. of channels, which is returned
+fn check_frames<T>(frames: &[Image<T>], empty: &[T]) -> Result<usize, ParsingError> {
+    let channels = frames.first().ok_or(ParsingError::EmptyBufferError)?.channels();
+
+    if frames.iter().any(|frame| frame.channels() != channels) {
+        return Err(ParsingError::ChannelMismatch)
+    }
+    check_empty(empty, channels)?;
+
+    Ok(channels)
+}
+
+/// Copies each frame into a new image of the given dimensions at the top left corner of its rectangle
+fn draw_frames<T: Clone>(frames: &[Image<T>], rects: &[(usize,usize,usize,usize)], dimensions: (usize,usize), channels: usize, empty: &[T]) -> Result<Image<T>, ParsingError> {
+    buffer_len(dimensions.0, dimensions.1, channels)?;
+    let mut canvas = filled_canvas(empty, dimensions.0*dimensions.1);
+
+    for (frame, (x, y, _, _)) in frames.iter().zip(rects) {
+        for (row_index, row) in frame.view().rows().enumerate() {
+            let start = ((y+row_index)*dimensions.0+x)*channels;
+            canvas[start..start+row.len()].clone_from_slice(row);
+        }
+    }
+
+    Image::new(canvas, dimensions.0, dimensions.1, channels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(value: u8, width: usize, height: usize) -> Image<u8> {
+        Image::new(vec![value; width*height], width, height, 1).unwrap()
+    }
+
+    #[test]
+    fn three_frames_fill_a_2_column_sheet() {
+        let (sheet, rects) = pack_grid(&[solid(1, 8, 8), solid(2, 8, 8), solid(3, 8, 8)], 2, 0, &[0]).unwrap();
+
+        assert_eq!(sheet.dimensions(), (16,16));
+        assert_eq!(rects, [(0,0,8,8), (8,0,8,8), (0,8,8,8)]);
+        assert_eq!([sheet.data()[0], sheet.data()[8], sheet.data()[8*16], sheet.data()[8*16+8]], [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn packed_frames_do_not_overlap() {
+        let sizes = [(5,3), (2,7), (9,2), (4,4), (1,1), (6,5), (3,3)];
+        let frames: Vec<Image<u8>> = sizes.iter().enumerate().map(|(index, (width, height))| solid(index as u8+1, *width, *height)).collect();
+
+        for padding in [0, 1, 2] {
+            let (atlas, rects) = pack_atlas(&frames, 12, padding, &[0]).unwrap();
+            assert!(atlas.width() <= 12);
+
+            for (index, rect) in rects.iter().enumerate() {
+                assert_eq!((rect.2, rect.3), sizes[index]);
+                for y in rect.1..rect.1+rect.3 {
+                    assert!(atlas.data()[y*atlas.width()+rect.0..y*atlas.width()+rect.0+rect.2].iter().all(|value| *value == index as u8+1));
+                }
+
+                for other in rects[index+1..].iter() {
+                    let apart = rect.0+rect.2+padding <= other.0 || other.0+other.2+padding <= rect.0
+                        || rect.1+rect.3+padding <= other.1 || other.1+other.3+padding <= rect.1;
+                    assert!(apart, "{:?} and {:?} overlap with a padding of {}", rect, other, padding);
+                }
+            }
+        }
+    }
+}
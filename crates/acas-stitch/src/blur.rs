@@ -0,0 +1,102 @@
+use crate::{check_buffer, AlphaChannel, ParsingError, StitchableType, StitchingResult};
+
+/// Blurs an image by averaging every pixel with the ones around it in a (2\*radius+1) x (2\*radius+1) square,
+/// done as a horizontal pass followed by a vertical one
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - radius - How many pixels on each side are averaged, 0 returns the image as is
+/// - alpha_channel - Which channel holds the alpha value, colors are weighted by it so transparent pixels dont darken the edges
+///
+/// Pixels past the edge of the image are clamped onto the closest pixel on the border.
+/// Returns ParsingError::DimensionsTooLarge if the window is too large to count
+pub fn box_blur<T>(buf: &[T], width: usize, height: usize, channels: usize, radius: usize, alpha_channel: AlphaChannel) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+    let alpha_index = alpha_channel.index(channels)?;
+
+    if radius == 0 {
+        return Ok((width,height,buf.to_vec()))
+    }
+
+    let max = T::maxvalue().to_f64();
+
+    // Premultiplied, so the color of transparent pixels does not bleed into their neighbours
+    let mut values: Vec<f64> = Vec::with_capacity(buf.len());
+    for pixel in buf.chunks(channels) {
+        let alpha = alpha_index.map_or(1.0, |index| pixel[index].to_f64()/max);
+        values.extend(pixel.iter().enumerate().map(|(c, value)| if Some(c) == alpha_index { value.to_f64() } else { value.to_f64()*alpha }));
+    }
+
+    let blurred = blur_values(&values, width, height, channels, radius)?;
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buf.len());
+    for pixel in blurred.chunks(channels) {
+        let alpha = alpha_index.map_or(1.0, |index| pixel[index]/max);
+        for (c, value) in pixel.iter().enumerate() {
+            let value = if Some(c) == alpha_index { *value } else if alpha > 0.0 { value/alpha } else { 0.0 };
+            result_buffer.push(T::from_f64(f64::min(value, max)));
+        }
+    }
+
+    Ok((width,height,result_buffer))
+}
+
+/// Box blurs a buffer of values with a horizontal pass followed by a vertical one, the kernel shared by box_blur() and drop_shadow()
+pub(crate) fn blur_values(values: &[f64], width: usize, height: usize, channels: usize, radius: usize) -> Result<Vec<f64>, ParsingError> {
+    let window = radius.checked_mul(2).and_then(|diameter| diameter.checked_add(1)).ok_or(ParsingError::DimensionsTooLarge)?;
+
+    let horizontal = blur_pass(values, width, height, channels, radius, window, (channels, width*channels));
+    Ok(blur_pass(&horizontal, height, width, channels, radius, window, (width*channels, channels)))
+}
+
+/// Averages every value with its neighbours along one axis, clamping at the ends of each line.
+/// length is the no. of pixels along the axis, lines the no. of lines across it,
+/// window the no. of values averaged (2\*radius+1) and strides the distance in values between neighbouring pixels along and across the axis
+fn blur_pass(values: &[f64], length: usize, lines: usize, channels: usize, radius: usize, window: usize, strides: (usize,usize)) -> Vec<f64> {
+    let mut blurred = vec![0.0; values.len()];
+
+    for line in 0..lines {
+        for position in 0..length {
+            for c in 0..channels {
+                let at = |position: usize| values[line*strides.1+position*strides.0+c];
+
+                let sum: f64 = (0..window)
+                    .map(|offset| at(position.saturating_add(offset).saturating_sub(radius).min(length-1)))
+                    .sum();
+                blurred[line*strides.1+position*strides.0+c] = sum/window as f64;
+            }
+        }
+    }
+
+    blurred
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radius_too_large_to_count_is_rejected() {
+        assert_eq!(box_blur(&[0u8, 255], 1, 1, 2, usize::MAX, AlphaChannel::Last), Err(ParsingError::DimensionsTooLarge));
+        assert_eq!(box_blur(&[0u8, 255], 1, 1, 2, usize::MAX/2+1, AlphaChannel::Last), Err(ParsingError::DimensionsTooLarge));
+    }
+
+    #[test]
+    fn single_white_pixel_spreads_into_3x3() {
+        let mut buf = vec![0u8; 5*5];
+        buf[2*5+2] = 255;
+
+        let (_, _, blurred) = box_blur(&buf, 5, 5, 1, 1, AlphaChannel::Opaque).unwrap();
+        for (index, value) in blurred.iter().enumerate() {
+            let (x, y) = (index%5, index/5);
+            // 255/9 rounded
+            let expected = if (1..4).contains(&x) && (1..4).contains(&y) { 28 } else { 0 };
+            assert_eq!(*value, expected, "pixel {},{}", x, y);
+        }
+    }
+}
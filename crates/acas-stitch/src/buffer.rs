@@ -0,0 +1,313 @@
+use crate::{Appendage, Joint, ParsingError, StitchableType, StitchingQuality, StitchingResult};
+
+/// An image buffer bundled together with its dimensions and channel count
+///
+/// The length of the data is always width\*height\*channels,
+/// this is checked whenever an Image is constructed, including when it is deserialized with the serde feature
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "RawImage<T>"))]
+pub struct Image<T> {
+    data: Vec<T>,
+    width: usize,
+    height: usize,
+    channels: usize
+}
+
+/// The fields of an Image as they are deserialized, before they are checked
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawImage<T> {
+    data: Vec<T>,
+    width: usize,
+    height: usize,
+    channels: usize
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<RawImage<T>> for Image<T> {
+    type Error = ParsingError;
+
+    fn try_from(raw: RawImage<T>) -> Result<Image<T>, ParsingError> {
+        Image::new(raw.data, raw.width, raw.height, raw.channels)
+    }
+}
+
+impl<T> Image<T> {
+    /// Creates an Image from a buffer of interleaved pixels
+    ///
+    /// # Arguments
+    ///
+    /// - data - The image
+    /// - width, height - Dimensions of image
+    /// - channels - No. of channels per pixel
+    pub fn new(data: Vec<T>, width: usize, height: usize, channels: usize) -> Result<Image<T>, ParsingError> {
+        crate::check_buffer(&data, channels, width, height)?;
+
+        Ok(Image { data, width, height, channels })
+    }
+
+    /// Creates an Image using u32 dimensions, like the ones returned by the image crate
+    ///
+    /// Returns ParsingError::DimensionsTooLarge if the dimensions do not fit in a usize on this target
+    pub fn from_u32_dimensions(data: Vec<T>, dimensions: (u32,u32), channels: usize) -> Result<Image<T>, ParsingError> {
+        let width = usize::try_from(dimensions.0).map_err(|_| ParsingError::DimensionsTooLarge)?;
+        let height = usize::try_from(dimensions.1).map_err(|_| ParsingError::DimensionsTooLarge)?;
+
+        Image::new(data, width, height, channels)
+    }
+
+    /// Creates an Image from the (Width, Height, Image buffer) returned by the free functions of this crate
+    pub fn from_result(result: StitchingResult<T>, channels: usize) -> Result<Image<T>, ParsingError> {
+        let (width, height, data) = result?;
+        Image::new(data, width, height, channels)
+    }
+
+    /// Width of the image in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the image in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// (Width, Height) of the image in pixels
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// (Width, Height) of the image as u32, like the image crate expects
+    ///
+    /// Returns ParsingError::DimensionsTooLarge instead of silently truncating
+    pub fn dimensions_u32(&self) -> Result<(u32,u32), ParsingError> {
+        let width = u32::try_from(self.width).map_err(|_| ParsingError::DimensionsTooLarge)?;
+        let height = u32::try_from(self.height).map_err(|_| ParsingError::DimensionsTooLarge)?;
+
+        Ok((width, height))
+    }
+
+    /// No. of channels per pixel
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// The underlying buffer of interleaved pixels
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    /// Consumes the Image, returning the underlying buffer
+    pub fn into_data(self) -> Vec<T> {
+        self.data
+    }
+
+    /// Consumes the Image, returning it in the (Width, Height, Image buffer) form used by the free functions
+    pub fn into_parts(self) -> (usize, usize, Vec<T>) {
+        (self.width, self.height, self.data)
+    }
+
+    /// Applies f to every value, keeping the dimensions and channel count
+    pub(crate) fn map<U>(&self, f: impl Fn(T) -> U) -> Image<U>
+    where T: Copy
+    {
+        Image { data: self.data.iter().map(|value| f(*value)).collect(), width: self.width, height: self.height, channels: self.channels }
+    }
+}
+
+/// A borrowed image buffer bundled together with its dimensions and channel count, the borrowing counterpart of Image
+///
+/// Like Image, the length of the data is checked to be width\*height\*channels when it is constructed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageView<'a, T> {
+    data: &'a [T],
+    width: usize,
+    height: usize,
+    channels: usize
+}
+
+impl<'a, T> ImageView<'a, T> {
+    /// Creates an ImageView over a buffer of interleaved pixels
+    ///
+    /// # Arguments
+    ///
+    /// - data - The image
+    /// - width, height - Dimensions of image
+    /// - channels - No. of channels per pixel
+    pub fn new(data: &'a [T], width: usize, height: usize, channels: usize) -> Result<ImageView<'a, T>, ParsingError> {
+        crate::check_buffer(data, channels, width, height)?;
+
+        Ok(ImageView { data, width, height, channels })
+    }
+
+    /// Width of the image in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the image in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// (Width, Height) of the image in pixels
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// No. of channels per pixel
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// The underlying buffer of interleaved pixels
+    pub fn data(&self) -> &'a [T] {
+        self.data
+    }
+
+    /// The pixel at (x, y), None if it lies outside the image
+    pub fn pixel(&self, x: usize, y: usize) -> Option<&'a [T]> {
+        if x >= self.width || y >= self.height {
+            return None
+        }
+
+        let index = (y*self.width+x)*self.channels;
+        Some(&self.data[index..index+self.channels])
+    }
+
+    /// Iterates over the rows of the image from top to bottom, each row holds width\*channels values
+    pub fn rows(&self) -> impl Iterator<Item = &'a [T]> {
+        self.data.chunks_exact(self.width*self.channels)
+    }
+
+    /// Iterates over the pixels of the image in raster order (left to right, then top to bottom) as (x, y, pixel)
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize, &'a [T])> {
+        let width = self.width;
+
+        self.data.chunks_exact(self.channels).enumerate().map(move |(index, pixel)| (index%width, index/width, pixel))
+    }
+}
+
+/// The pixel at (x, y) of an image buffer, None if it lies outside the image or the buffer does not match the dimensions
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - x, y - Coordinates of the pixel
+pub fn get_pixel<T>(buf: &[T], width: usize, height: usize, channels: usize, x: usize, y: usize) -> Option<&[T]> {
+    ImageView::new(buf, width, height, channels).ok()?.pixel(x, y)
+}
+
+/// Overwrites the pixel at (x, y) of an image buffer
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - x, y - Coordinates of the pixel
+/// - value - The new pixel, with one value per channel
+///
+/// Returns ParsingError::PixelOutOfBounds if (x, y) lies outside the image and ParsingError::ChannelMismatch if value has the wrong length
+pub fn set_pixel<T: Clone>(buf: &mut [T], width: usize, height: usize, channels: usize, x: usize, y: usize, value: &[T]) -> Result<(), ParsingError> {
+    crate::check_buffer(buf, channels, width, height)?;
+    if value.len() != channels {
+        return Err(ParsingError::ChannelMismatch)
+    }
+    if x >= width || y >= height {
+        return Err(ParsingError::PixelOutOfBounds)
+    }
+
+    let index = (y*width+x)*channels;
+    buf[index..index+channels].clone_from_slice(value);
+
+    Ok(())
+}
+
+impl<'a, T> From<&'a Image<T>> for ImageView<'a, T> {
+    fn from(image: &'a Image<T>) -> ImageView<'a, T> {
+        image.view()
+    }
+}
+
+impl<T> Image<T> {
+    /// Borrows the Image as an ImageView
+    pub fn view(&self) -> ImageView<'_, T> {
+        ImageView { data: &self.data, width: self.width, height: self.height, channels: self.channels }
+    }
+
+    /// Iterates over the pixels of the image in raster order as (x, y, pixel), see ImageView::pixels()
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize, &[T])> {
+        self.view().pixels()
+    }
+
+    /// Same as pixels() but the pixels can be modified in place
+    pub fn pixels_mut(&mut self) -> impl Iterator<Item = (usize, usize, &mut [T])> {
+        let width = self.width;
+
+        self.data.chunks_exact_mut(self.channels).enumerate().map(move |(index, pixel)| (index%width, index/width, pixel))
+    }
+}
+
+impl<T> Image<T>
+where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+{
+    /// Rotates the image, see rotate_with_quality()
+    ///
+    /// # Arguments
+    ///
+    /// - empty - Empty space will be filled with this value
+    /// - angle - The angle of rotation (in radians),
+    ///   - positive => Anticlockwise,
+    ///   - negative => Clockwise
+    /// - quality - The rotation algorithm to use
+    pub fn rotate(&self, empty: &[T], angle: f64, quality: StitchingQuality) -> Result<Image<T>, ParsingError> {
+        let result = crate::rotate_keyed(&self.data, empty, self.channels, self.dimensions(), &crate::Rotation::new(angle), &quality, crate::ModeFilter::packed(None));
+
+        Image::from_result(result, self.channels)
+    }
+
+    /// Stitches an appendage onto this image, see stitch_appendage()
+    ///
+    /// # Arguments
+    ///
+    /// - appendage - The appendage image
+    /// - joint - Where the appendage is attached to this image, see Joint
+    /// - empty - Equivalent of empty pixel
+    /// - quality - The rotation algorithm used for the appendage
+    ///
+    /// One of the images may have one channel less than the other, see stitch_mixed_channels(),
+    /// the result then has as many channels as the richer image
+    pub fn stitch(&self, appendage: &Image<T>, joint: Joint, empty: &[T], quality: StitchingQuality) -> Result<Image<T>, ParsingError> {
+        let appendage_view = Appendage { buf: &appendage.data, dimensions: appendage.dimensions(), joint };
+        let result = crate::stitch_mixed_channels(&self.data, self.channels, self.dimensions(), &appendage_view, appendage.channels, empty, quality);
+
+        Image::from_result(result, usize::max(self.channels, appendage.channels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_are_strided_in_raster_order() {
+        let buf: Vec<u8> = (0..16).collect();
+        let pixels: Vec<(usize, usize, &[u8])> = ImageView::new(&buf, 2, 2, 4).unwrap().pixels().collect();
+
+        assert_eq!(pixels, [(0, 0, &buf[0..4]), (1, 0, &buf[4..8]), (0, 1, &buf[8..12]), (1, 1, &buf[12..16])]);
+    }
+
+    #[test]
+    fn out_of_range_pixels_are_rejected() {
+        let mut buf: Vec<u8> = (0..12).collect();
+
+        assert_eq!(get_pixel(&buf, 3, 2, 2, 1, 1), Some(&[8u8, 9][..]));
+        assert_eq!(get_pixel(&buf, 3, 2, 2, 3, 0), None);
+        assert_eq!(get_pixel(&buf, 3, 2, 2, 0, 2), None);
+        assert_eq!(set_pixel(&mut buf, 3, 2, 2, 3, 0, &[1, 1]), Err(ParsingError::PixelOutOfBounds));
+        assert_eq!(set_pixel(&mut buf, 3, 2, 2, usize::MAX, usize::MAX, &[1, 1]), Err(ParsingError::PixelOutOfBounds));
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use crate::{check_buffer, check_empty, ParsingError, StitchableType, StitchingResult};
+
+/// Tints an image towards a color by multiplying its color channels with it,
+/// useful for making color variants of an appendage before stitching it
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - color - The color to multiply by, with one value per channel
+/// - strength - How strongly the tint is applied, 0.0 leaves the image as is and 1.0 fully multiplies by color.
+///   Values outside 0.0..=1.0 are clamped
+///
+/// For 4 channel images the last channel is alpha, which is left untouched
+pub fn tint<T>(buf: &[T], width: usize, height: usize, channels: usize, color: &[T], strength: f64) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+    if color.len() != channels {
+        return Err(ParsingError::ChannelMismatch)
+    }
+
+    let max = T::maxvalue().to_f64();
+    let strength = strength.clamp(0.0, 1.0);
+    let color_channels = if channels == 4 { 3 } else { channels };
+
+    let factors: Vec<f64> = color.iter().map(|value| 1.0 - strength + strength*value.to_f64()/max).collect();
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buf.len());
+
+    for pixel in buf.chunks(channels) {
+        for (c, value) in pixel.iter().enumerate() {
+            if c < color_channels {
+                result_buffer.push(T::from_f64(f64::min(value.to_f64()*factors[c], max)));
+            } else {
+                result_buffer.push(*value);
+            }
+        }
+    }
+
+    Ok((width,height,result_buffer))
+}
+
+/// Recolors an image by looking the luminance of every pixel up in a color ramp, e.g. for duotone or heat map effects
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - ramp - The colors that luminance is mapped onto from darkest to brightest, with one value per channel.
+///   Luminances between two colors are interpolated between them
+///
+/// Luminance is taken from the first 3 channels with the Rec. 709 weights, or from the first channel for images with less than 3.
+/// For 4 channel images the last channel is alpha, which is left untouched and ignored in the ramp, like tint().
+/// Returns ParsingError::EmptyRamp if there are no colors in the ramp
+pub fn map_colors<T>(buf: &[T], width: usize, height: usize, channels: usize, ramp: &[&[T]]) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+    if ramp.is_empty() {
+        return Err(ParsingError::EmptyRamp)
+    }
+    if ramp.iter().any(|color| color.len() != channels) {
+        return Err(ParsingError::ChannelMismatch)
+    }
+
+    let max = T::maxvalue().to_f64();
+    let color_channels = if channels == 4 { 3 } else { channels };
+    let last = (ramp.len()-1) as f64;
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buf.len());
+
+    for pixel in buf.chunks(channels) {
+        let luminance = if color_channels >= 3 {
+            0.2126*pixel[0].to_f64() + 0.7152*pixel[1].to_f64() + 0.0722*pixel[2].to_f64()
+        } else {
+            pixel[0].to_f64()
+        };
+
+        let position = (luminance/max).clamp(0.0, 1.0)*last;
+        let lower = position.floor() as usize;
+        let upper = usize::min(lower+1, ramp.len()-1);
+        let fraction = position - lower as f64;
+
+        for (c, value) in pixel.iter().enumerate() {
+            if c < color_channels {
+                result_buffer.push(T::from_f64(ramp[lower][c].to_f64()*(1.0-fraction) + ramp[upper][c].to_f64()*fraction));
+            } else {
+                result_buffer.push(*value);
+            }
+        }
+    }
+
+    Ok((width,height,result_buffer))
+}
+
+/// Replaces every pixel that exactly equals a "from" color with its "to" color, other pixels are left untouched.
+/// Wont introduce any colors other than the ones given, so it can be combined with the rotations without losing the palette
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - mapping - (from, to) pairs of colors, with one value per channel.
+///   The colors are all swapped at once, so a pixel is never swapped twice, and the first pair wins if a color appears twice
+pub fn palette_swap<T>(buf: &[T], width: usize, height: usize, channels: usize, mapping: &[(&[T], &[T])]) -> StitchingResult<T>
+where T: Clone, [T]: Eq + std::hash::Hash
+{
+    check_buffer(buf, channels, width, height)?;
+
+    let mut swaps: HashMap<&[T], &[T]> = HashMap::with_capacity(mapping.len());
+    for (from, to) in mapping {
+        if from.len() != channels || to.len() != channels {
+            return Err(ParsingError::ChannelMismatch)
+        }
+        swaps.entry(*from).or_insert(*to);
+    }
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buf.len());
+
+    for pixel in buf.chunks(channels) {
+        result_buffer.extend_from_slice(swaps.get(pixel).copied().unwrap_or(pixel));
+    }
+
+    Ok((width,height,result_buffer))
+}
+
+/// Fills the region of empty pixels connected to a starting pixel with a color,
+/// e.g. to bake a sprite onto a solid background without filling the holes enclosed by it
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - start - (x, y) of the pixel to start filling from, if it isnt empty the image is returned as is
+/// - new_color - The fill color, with one value per channel
+/// - empty - Equivalent of empty pixel, only pixels exactly equal to it are filled
+///
+/// Pixels are connected through their 4 direct neighbours, so the fill wont leak through diagonal gaps.
+/// Returns ParsingError::PixelOutOfBounds if start lies outside the image
+pub fn flood_fill<T>(buf: &[T], width: usize, height: usize, channels: usize, start: (usize,usize), new_color: &[T], empty: &[T]) -> StitchingResult<T>
+where T: Clone, [T]: Eq
+{
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+    if new_color.len() != channels {
+        return Err(ParsingError::ChannelMismatch)
+    }
+    if start.0 >= width || start.1 >= height {
+        return Err(ParsingError::PixelOutOfBounds)
+    }
+
+    let mut result_buffer = buf.to_vec();
+    if new_color == empty {
+        return Ok((width,height,result_buffer))
+    }
+
+    // Pixels are filled as soon as they are pushed so none is pushed twice, an explicit stack keeps large regions from overflowing the call stack
+    let mut stack: Vec<(usize,usize)> = Vec::new();
+    let fill = |x: usize, y: usize, result_buffer: &mut [T], stack: &mut Vec<(usize,usize)>| {
+        let index = (y*width+x)*channels;
+        if result_buffer[index..index+channels] == *empty {
+            result_buffer[index..index+channels].clone_from_slice(new_color);
+            stack.push((x, y));
+        }
+    };
+
+    fill(start.0, start.1, &mut result_buffer, &mut stack);
+
+    while let Some((x, y)) = stack.pop() {
+        if x > 0 { fill(x-1, y, &mut result_buffer, &mut stack) }
+        if x+1 < width { fill(x+1, y, &mut result_buffer, &mut stack) }
+        if y > 0 { fill(x, y-1, &mut result_buffer, &mut stack) }
+        if y+1 < height { fill(x, y+1, &mut result_buffer, &mut stack) }
+    }
+
+    Ok((width,height,result_buffer))
+}
+
+/// Counts how often each distinct pixel appears in an image
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - channels - No. of channels per pixel
+///
+/// The pixels are returned in the order they first appear in, along with their counts
+pub fn distinct_colors<T>(buf: &[T], channels: usize) -> Result<Vec<(&[T], usize)>, ParsingError>
+where [T]: Eq + std::hash::Hash
+{
+    check_pixels(buf, channels)?;
+
+    let mut indices: HashMap<&[T], usize> = HashMap::new();
+    let mut colors: Vec<(&[T], usize)> = Vec::new();
+
+    for pixel in buf.chunks(channels) {
+        let index = *indices.entry(pixel).or_insert_with(|| {
+            colors.push((pixel, 0));
+            colors.len()-1
+        });
+        colors[index].1 += 1;
+    }
+
+    Ok(colors)
+}
+
+/// The distinct pixels of an image in the order they first appear in, see distinct_colors().
+/// Since the fancy and fast rotations wont introduce new colors, the palette of a rotated image is always a subset of the original's
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - channels - No. of channels per pixel
+pub fn palette<T>(buf: &[T], channels: usize) -> Result<Vec<Vec<T>>, ParsingError>
+where T: Clone, [T]: Eq + std::hash::Hash
+{
+    Ok(distinct_colors(buf, channels)?.into_iter().map(|(color, _)| color.to_vec()).collect())
+}
+
+/// Checks that a buffer holds a whole number of pixels, for functions that dont need the dimensions
+fn check_pixels<T>(buf: &[T], channels: usize) -> Result<(),ParsingError> {
+    if channels == 0 {
+        return Err(ParsingError::ZeroChannels)
+    }
+    if buf.is_empty() {
+        return Err(ParsingError::EmptyBufferError)
+    }
+    if buf.len() % channels != 0 {
+        // The length that would complete the last pixel
+        return Err(ParsingError::BufferSizeMismatch { expected: buf.len() + channels - buf.len()%channels, actual: buf.len() })
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tint_strength_bounds() {
+        let buf: Vec<u8> = vec![200,100,50,128, 10,20,30,255];
+
+        assert_eq!(tint(&buf, 2, 1, 4, &[0,0,0,0], 0.0), Ok((2,1,buf.clone())));
+        // Gray halves every color channel alike and leaves alpha alone
+        assert_eq!(tint(&buf, 2, 1, 4, &[128,128,128,0], 1.0), Ok((2,1,vec![100,50,25,128, 5,10,15,255])));
+    }
+
+    #[test]
+    fn palette_swap_red_to_green() {
+        let (red, green, blue): (&[u8], &[u8], &[u8]) = (&[255,0,0], &[0,255,0], &[0,0,255]);
+        let buf = [red, blue, red, red].concat();
+
+        assert_eq!(palette_swap(&buf, 2, 2, 3, &[(red, green)]), Ok((2,2,[green, blue, green, green].concat())));
+    }
+
+    #[test]
+    fn distinct_colors_counts_each_color() {
+        let buf: Vec<u8> = vec![1,2, 3,4, 1,2, 1,2];
+
+        assert_eq!(distinct_colors(&buf, 2), Ok(vec![(&[1u8,2][..], 3), (&[3u8,4][..], 1)]));
+        assert_eq!(palette(&buf, 2), Ok(vec![vec![1,2], vec![3,4]]));
+    }
+
+    #[test]
+    fn flood_fill_does_not_leak_into_a_ring() {
+        // A 3x3 ring with a hole in the middle, inside a 5x5 background
+        let mut buf = vec![0u8; 5*5];
+        for y in 1..4 {
+            for x in 1..4 {
+                if (x, y) != (2, 2) {
+                    buf[y*5+x] = 1;
+                }
+            }
+        }
+
+        let (_, _, filled) = flood_fill(&buf, 5, 5, 1, (0,0), &[7], &[0]).unwrap();
+        for (index, value) in filled.iter().enumerate() {
+            let expected = if buf[index] == 1 { 1 } else if index == 2*5+2 { 0 } else { 7 };
+            assert_eq!(*value, expected, "pixel {},{}", index%5, index/5);
+        }
+    }
+
+    #[test]
+    fn gradient_maps_onto_a_blue_to_red_ramp() {
+        let buf: Vec<u8> = [0u8, 85, 170, 255].iter().flat_map(|value| [*value, *value, *value, 200]).collect();
+        let (blue, red): (&[u8], &[u8]) = (&[0,0,255,0], &[255,0,0,0]);
+
+        let (_, _, mapped) = map_colors(&buf, 4, 1, 4, &[blue, red]).unwrap();
+        assert_eq!(mapped[0..4], [0,0,255,200]);
+        assert_eq!(mapped[12..16], [255,0,0,200]);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{buffer_len, check_buffer, check_empty, EdgeMode, ImageView, ParsingError, PositionedResult, StitchableType, StitchingResult};
+
+/// Finds the smallest rectangle containing every non empty pixel, useful for trimming the margins left by rotation
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - empty - Pixels equal to this are considered empty,
+///   for 4 channel images any pixel with an alpha of 0 is considered empty as well
+///
+/// Returns (min x, min y, max x, max y) with the maximums being inclusive, or None if the whole image is empty
+pub fn content_bounds<T>(buf: &[T], width: usize, height: usize, channels: usize, empty: &[T]) -> Result<Option<(usize,usize,usize,usize)>, ParsingError>
+where T: StitchableType, [T]: Eq
+{
+    let image = ImageView::new(buf, width, height, channels)?;
+    check_empty(empty, channels)?;
+
+    let mut bounds: Option<(usize,usize,usize,usize)> = None;
+
+    for (x, y, pixel) in image.pixels() {
+        let transparent = channels == 4 && pixel[3].to_f64() == 0.0;
+        if pixel == empty || transparent {
+            continue
+        }
+
+        bounds = Some(match bounds {
+            None => (x, y, x, y),
+            Some((min_x, min_y, max_x, max_y)) => (usize::min(min_x, x), usize::min(min_y, y), usize::max(max_x, x), usize::max(max_y, y))
+        });
+    }
+
+    Ok(bounds)
+}
+
+/// Crops away the empty rows and columns around an image, e.g. the margins fast_rotate() leaves behind
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - empty - Pixels equal to this are considered empty, see content_bounds()
+///
+/// Returns the trimmed image along with the position of its top left pixel in the original,
+/// so a point (x,y) in the original ends up at (x-offset.0,y-offset.1).
+/// Returns ParsingError::EmptyBufferError if every pixel is empty as there would be nothing left
+pub fn trim<T>(buf: &[T], width: usize, height: usize, channels: usize, empty: &[T]) -> PositionedResult<T>
+where T: StitchableType, [T]: Eq
+{
+    let (min_x, min_y, max_x, max_y) = content_bounds(buf, width, height, channels, empty)?.ok_or(ParsingError::EmptyBufferError)?;
+
+    let cropped = crop(buf, width, height, channels, (min_x, min_y), (max_x-min_x+1, max_y-min_y+1))?;
+
+    Ok((cropped,(min_x,min_y)))
+}
+
+/// Copies a rectangular window out of an image
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - position - (x, y) of the top left pixel of the window
+/// - size - (Width, Height) of the window
+///
+/// Returns ParsingError::WindowOutOfBounds if the window does not fit inside the image
+/// and ParsingError::EmptyBufferError if it has no pixels
+pub fn crop<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, position: (usize,usize), size: (usize,usize)) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+    let ((x, y), (w, h)) = (position, size);
+
+    if x.checked_add(w).map_or(true, |right| right > width) || y.checked_add(h).map_or(true, |bottom| bottom > height) {
+        return Err(ParsingError::WindowOutOfBounds)
+    }
+    if w == 0 || h == 0 {
+        return Err(ParsingError::EmptyBufferError)
+    }
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(w*h*channels);
+
+    for row in y..y+h {
+        let start = (row*width+x)*channels;
+        result_buffer.extend_from_slice(&buf[start..start+w*channels]);
+    }
+
+    Ok((w,h,result_buffer))
+}
+
+/// Pads an image on all sides with mirrored copies of its border, e.g. before blurring so the edges don't fade into the background
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - amount - No. of pixels added on each side, the result has dimensions (width+2\*amount) x (height+2\*amount)
+///
+/// The border pixel itself is repeated, so padding the row A B C by 1 gives A A B C C, the same as EdgeMode::Reflect.
+/// Amounts larger than the image keep mirroring back and forth
+pub fn pad_reflect<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, amount: usize) -> StitchingResult<T> {
+    pad(buf, width, height, channels, amount, EdgeMode::Reflect)
+}
+
+/// Pads an image on all sides with a constant pixel
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - amount - No. of pixels added on each side, the result has dimensions (width+2\*amount) x (height+2\*amount)
+/// - value - The pixel the padding is filled with
+///
+/// Returns ParsingError::EmptyPixelMismatch if value does not have one value per channel
+pub fn pad_constant<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, amount: usize, value: &[T]) -> StitchingResult<T> {
+    check_empty(value, channels)?;
+
+    pad(buf, width, height, channels, amount, EdgeMode::Fill(value))
+}
+
+/// Pads an image by amount on all sides, pixels outside it are filled as edge_mode says
+fn pad<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, amount: usize, edge_mode: EdgeMode<T>) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+
+    let overflow = || ParsingError::DimensionsTooLarge;
+    let new_width = amount.checked_mul(2).and_then(|padding| padding.checked_add(width)).ok_or_else(overflow)?;
+    let new_height = amount.checked_mul(2).and_then(|padding| padding.checked_add(height)).ok_or_else(overflow)?;
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(new_width, new_height, channels)?);
+
+    for y in 0..new_height as isize {
+        for x in 0..new_width as isize {
+            let (x, y) = (x-amount as isize, y-amount as isize);
+
+            match edge_mode {
+                EdgeMode::Fill(value) if x < 0 || y < 0 || x >= width as isize || y >= height as isize => result_buffer.extend_from_slice(value),
+                _ => {
+                    let index = (edge_mode.resolve(y, height)*width+edge_mode.resolve(x, width))*channels;
+                    result_buffer.extend_from_slice(&buf[index..index+channels]);
+                }
+            }
+        }
+    }
+
+    Ok((new_width,new_height,result_buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_of_a_single_off_center_pixel() {
+        let mut buf = vec![0u8; 5*4*4];
+        assert_eq!(content_bounds(&buf, 5, 4, 4, &[0,0,0,0]), Ok(None));
+
+        buf[(2*5+3)*4..(2*5+3)*4+4].copy_from_slice(&[1,2,3,255]);
+        assert_eq!(content_bounds(&buf, 5, 4, 4, &[0,0,0,0]), Ok(Some((3,2,3,2))));
+    }
+
+    #[test]
+    fn trim_restores_a_padded_sprite() {
+        let sprite: Vec<u8> = (0..3*2).flat_map(|index| [index as u8+1, 0, 0, 255]).collect();
+        // The sprite with 2 transparent pixels on every side
+        let mut padded = vec![0u8; 7*6*4];
+        for y in 0..2 {
+            padded[((y+2)*7+2)*4..((y+2)*7+5)*4].copy_from_slice(&sprite[y*3*4..(y+1)*3*4]);
+        }
+
+        assert_eq!(trim(&padded, 7, 6, 4, &[0,0,0,0]), Ok(((3,2,sprite),(2,2))));
+    }
+
+    #[test]
+    fn crop_the_center_of_a_4x4_image() {
+        let buf: Vec<u8> = (0..16u8).flat_map(|index| [index, 100+index]).collect();
+
+        assert_eq!(crop(&buf, 4, 4, 2, (1,1), (2,2)), Ok((2,2,vec![5,105, 6,106, 9,109, 10,110])));
+        assert_eq!(crop(&buf, 4, 4, 2, (3,0), (2,1)), Err(ParsingError::WindowOutOfBounds));
+        assert_eq!(crop(&buf, 4, 4, 2, (0,usize::MAX), (1,2)), Err(ParsingError::WindowOutOfBounds));
+    }
+
+    #[test]
+    fn reflect_pad_mirrors_the_border_columns() {
+        let row = [1u8, 2, 3];
+
+        assert_eq!(pad_reflect(&row, 3, 1, 1, 1), Ok((5,3,[1,1,2,3,3].repeat(3))));
+        assert_eq!(pad_reflect(&row, 3, 1, 1, 2).unwrap().2[0..7], [2,1,1,2,3,3,2]);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{Image, Joint, ParsingError, StitchableType, StitchingQuality};
+
+/// An Image whose subpixel type is only known at runtime, like the DynamicImage of the image crate
+///
+/// Useful for passing images of any depth across an API boundary, e.g. after decoding a file.
+/// Images of different depths are never converted implicitly, use to_u8(), to_u16() or to_f32() to convert them.
+/// f32 images hold values in 0.0..=1.0
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynImage {
+    /// 8 bits per channel
+    U8(Image<u8>),
+    /// 16 bits per channel
+    U16(Image<u16>),
+    /// A 32 bit float per channel
+    F32(Image<f32>)
+}
+
+impl From<Image<u8>> for DynImage {
+    fn from(image: Image<u8>) -> DynImage {
+        DynImage::U8(image)
+    }
+}
+
+impl From<Image<u16>> for DynImage {
+    fn from(image: Image<u16>) -> DynImage {
+        DynImage::U16(image)
+    }
+}
+
+impl From<Image<f32>> for DynImage {
+    fn from(image: Image<f32>) -> DynImage {
+        DynImage::F32(image)
+    }
+}
+
+impl DynImage {
+    /// (Width, Height) of the image in pixels
+    pub fn dimensions(&self) -> (usize, usize) {
+        match self {
+            DynImage::U8(image) => image.dimensions(),
+            DynImage::U16(image) => image.dimensions(),
+            DynImage::F32(image) => image.dimensions()
+        }
+    }
+
+    /// No. of channels per pixel
+    pub fn channels(&self) -> usize {
+        match self {
+            DynImage::U8(image) => image.channels(),
+            DynImage::U16(image) => image.channels(),
+            DynImage::F32(image) => image.channels()
+        }
+    }
+
+    /// Converts the image to 8 bits per channel, scaling the values into the new range and clamping f32 values to 0.0..=1.0
+    pub fn to_u8(&self) -> Image<u8> {
+        match self {
+            DynImage::U8(image) => image.clone(),
+            _ => self.convert(|value| u8::from_f64(value*u8::MAX as f64))
+        }
+    }
+
+    /// Converts the image to 16 bits per channel, scaling the values into the new range and clamping f32 values to 0.0..=1.0
+    pub fn to_u16(&self) -> Image<u16> {
+        match self {
+            DynImage::U16(image) => image.clone(),
+            _ => self.convert(|value| u16::from_f64(value*u16::MAX as f64))
+        }
+    }
+
+    /// Converts the image to floats in 0.0..=1.0
+    pub fn to_f32(&self) -> Image<f32> {
+        match self {
+            DynImage::F32(image) => image.clone(),
+            _ => self.convert(|value| value as f32)
+        }
+    }
+
+    /// Maps every value to 0.0..=1.0 and converts it with from_unit
+    fn convert<U>(&self, from_unit: impl Fn(f64) -> U) -> Image<U> {
+        match self {
+            DynImage::U8(image) => image.map(|value| from_unit(value.to_f64()/u8::MAX as f64)),
+            DynImage::U16(image) => image.map(|value| from_unit(value.to_f64()/u16::MAX as f64)),
+            DynImage::F32(image) => image.map(|value| from_unit((value as f64).clamp(0.0, 1.0)))
+        }
+    }
+
+    /// Rotates the image, see Image::rotate()
+    ///
+    /// # Arguments
+    ///
+    /// - empty - Empty space will be filled with this value, given in 0.0..=1.0 so the same pixel works for every depth
+    /// - angle - The angle of rotation (in radians),
+    ///   - positive => Anticlockwise,
+    ///   - negative => Clockwise
+    /// - quality - The rotation algorithm to use
+    ///
+    /// The result has the same depth. f32 pixels are only ever copied, so their values are kept exactly
+    pub fn rotate(&self, empty: &[f64], angle: f64, quality: StitchingQuality) -> Result<DynImage, ParsingError> {
+        match self {
+            DynImage::U8(image) => Ok(DynImage::U8(image.rotate(&empty_pixel(empty), angle, quality)?)),
+            DynImage::U16(image) => Ok(DynImage::U16(image.rotate(&empty_pixel(empty), angle, quality)?)),
+            DynImage::F32(image) => {
+                // Floats are not Eq, their bit patterns are, and the fancy algorithm only compares pixels for equality
+                let empty: Vec<u32> = empty.iter().map(|value| (*value as f32).to_bits()).collect();
+                let rotated = image.map(f32::to_bits).rotate(&empty, angle, quality)?;
+
+                Ok(DynImage::F32(rotated.map(f32::from_bits)))
+            }
+        }
+    }
+
+    /// Stitches an appendage of the same depth onto this image, see Image::stitch()
+    ///
+    /// # Arguments
+    ///
+    /// - appendage - The appendage image
+    /// - joint - Where the appendage is attached to this image, see Joint
+    /// - empty - Equivalent of empty pixel, given in 0.0..=1.0 so the same pixel works for every depth
+    /// - quality - The rotation algorithm used for the appendage
+    ///
+    /// f32 images are blended at 32 bit integer precision, values outside 0.0..=1.0 are clamped.
+    /// Returns ParsingError::DepthMismatch if the appendage has a different depth
+    pub fn stitch(&self, appendage: &DynImage, joint: Joint, empty: &[f64], quality: StitchingQuality) -> Result<DynImage, ParsingError> {
+        match (self, appendage) {
+            (DynImage::U8(image), DynImage::U8(appendage)) => Ok(DynImage::U8(image.stitch(appendage, joint, &empty_pixel(empty), quality)?)),
+            (DynImage::U16(image), DynImage::U16(appendage)) => Ok(DynImage::U16(image.stitch(appendage, joint, &empty_pixel(empty), quality)?)),
+            (DynImage::F32(image), DynImage::F32(appendage)) => {
+                let to_u32 = |value: f32| u32::from_f64((value as f64).clamp(0.0, 1.0)*u32::MAX as f64);
+                let stitched = image.map(to_u32).stitch(&appendage.map(to_u32), joint, &empty_pixel(empty), quality)?;
+
+                Ok(DynImage::F32(stitched.map(|value| (value.to_f64()/u32::MAX as f64) as f32)))
+            },
+            _ => Err(ParsingError::DepthMismatch)
+        }
+    }
+}
+
+/// Converts an empty pixel given in 0.0..=1.0 to a subpixel type
+fn empty_pixel<T: StitchableType>(empty: &[f64]) -> Vec<T> {
+    empty.iter().map(|value| T::from_f64(value.clamp(0.0, 1.0)*T::maxvalue().to_f64())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn u16_image_round_trips() {
+        let data: Vec<u16> = (0..5*3*4).map(|index| if index%4 == 3 { u16::MAX } else { (index*1021) as u16 }).collect();
+        let image = Image::new(data, 5, 3, 4).unwrap();
+        let dynamic = DynImage::from(image.clone());
+
+        assert_eq!((dynamic.dimensions(), dynamic.channels()), ((5,3), 4));
+        assert_eq!(dynamic.to_u16(), image);
+        assert_eq!(DynImage::from(dynamic.to_f32()).to_u16(), image);
+    }
+}
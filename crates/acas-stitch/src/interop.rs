@@ -0,0 +1,63 @@
+use image::{ImageBuffer, Pixel, Rgba, RgbaImage};
+
+use crate::{Image, ParsingError};
+
+/// Converts a buffer of the image crate, e.g. RgbaImage, RgbImage or GrayImage, into an Image
+///
+/// The channel count is taken from the pixel type, so RgbaImage gives an Image with 4 channels.
+/// Fails with the same errors as Image::new(), e.g. ParsingError::EmptyBufferError for a 0x0 image
+impl<P> TryFrom<ImageBuffer<P, Vec<P::Subpixel>>> for Image<P::Subpixel>
+where P: Pixel
+{
+    type Error = ParsingError;
+
+    fn try_from(image: ImageBuffer<P, Vec<P::Subpixel>>) -> Result<Image<P::Subpixel>, ParsingError> {
+        let dimensions = image.dimensions();
+
+        Image::from_u32_dimensions(image.into_raw(), dimensions, P::CHANNEL_COUNT as usize)
+    }
+}
+
+impl<T> Image<T> {
+    /// Converts the Image back into a buffer of the image crate, moving the data without copying it
+    ///
+    /// The pixel type has to be given, e.g. `image.into_image_buffer::<Rgba<u8>>()` for an RgbaImage.
+    /// Returns ParsingError::ChannelMismatch if the pixel type has a different number of channels than the Image
+    /// and ParsingError::DimensionsTooLarge if the dimensions do not fit in a u32
+    pub fn into_image_buffer<P>(self) -> Result<ImageBuffer<P, Vec<T>>, ParsingError>
+    where P: Pixel<Subpixel = T>
+    {
+        if self.channels() != P::CHANNEL_COUNT as usize {
+            return Err(ParsingError::ChannelMismatch)
+        }
+
+        let (width, height) = self.dimensions_u32()?;
+
+        Ok(ImageBuffer::from_raw(width, height, self.into_data()).expect("Image length is checked on construction"))
+    }
+}
+
+impl Image<u8> {
+    /// Converts an 8 bit Image with 4 channels into an RgbaImage, moving the data without copying it
+    ///
+    /// Returns None if the Image does not have 4 channels or its dimensions do not fit in a u32,
+    /// use into_image_buffer() to find out which
+    pub fn to_rgba_image(self) -> Option<RgbaImage> {
+        self.into_image_buffer::<Rgba<u8>>().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn into_image_buffer_shares_the_data() {
+        let image = Image::new(vec![7u8; 3*2*4], 3, 2, 4).unwrap();
+        let data = image.data().as_ptr();
+
+        let buffer = image.into_image_buffer::<Rgba<u8>>().unwrap();
+        assert_eq!(buffer.dimensions(), (3,2));
+        assert_eq!(buffer.as_raw().as_ptr(), data);
+    }
+}
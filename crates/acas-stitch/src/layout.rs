@@ -0,0 +1,105 @@
+use crate::{check_buffer, check_empty, fast_rotate, StitchingResult};
+
+/// How the channels of an image buffer are arranged
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PixelLayout {
+    /// The channels of each pixel are stored together, RGBARGBA..., which is what every function expects by default
+    #[default]
+    Interleaved,
+    /// Each channel is stored as a whole plane of its own, RRRR...GGGG...BBBB...AAAA...,
+    /// as used by some decoders and scientific imaging
+    Planar
+}
+
+/// Converts an interleaved image buffer into a planar one
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+pub fn to_planar<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buf.len());
+    for c in 0..channels {
+        result_buffer.extend(buf.iter().skip(c).step_by(channels).cloned());
+    }
+
+    Ok((width,height,result_buffer))
+}
+
+/// Converts a planar image buffer into an interleaved one, the inverse of to_planar()
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+pub fn to_interleaved<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+
+    let plane = width*height;
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buf.len());
+    for index in 0..plane {
+        result_buffer.extend((0..channels).map(|c| buf[c*plane+index].clone()));
+    }
+
+    Ok((width,height,result_buffer))
+}
+
+/// Same as fast_rotate() but the buffer may be planar, the result then is too
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - empty - Empty space will be filled with this value, always a single pixel with one value per channel
+/// - channels - No. of channels per pixel
+/// - width, height - Dimensions of image
+/// - angle - The angle of rotation (in radians),
+///   - positive => Anticlockwise,
+///   - negative => Clockwise
+/// - layout - How the channels of buf are arranged
+///
+/// Each plane is rotated on its own as a single channel image, so the planes are never interleaved
+pub fn fast_rotate_with_layout<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64, layout: PixelLayout) -> StitchingResult<T> {
+    if layout == PixelLayout::Interleaved {
+        return fast_rotate(buf, empty, channels, width, height, angle)
+    }
+
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+
+    let plane = width*height;
+    let rotate_plane = |c: usize| fast_rotate(&buf[c*plane..(c+1)*plane], &empty[c..c+1], 1, width, height, angle);
+
+    // Every plane rotates to the same dimensions, so the first one gives them
+    let (new_width, new_height, first) = rotate_plane(0)?;
+    let mut result_buffer: Vec<T> = Vec::with_capacity(first.len()*channels);
+    result_buffer.extend(first);
+
+    for c in 1..channels {
+        result_buffer.extend(rotate_plane(c)?.2);
+    }
+
+    Ok((new_width,new_height,result_buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planar_rotation_matches_interleaved() {
+        let (width, height, channels) = (5, 3, 4);
+        let buf: Vec<u8> = (0..width*height*channels).map(|value| (value*7%251) as u8).collect();
+        let planar = to_planar(&buf, width, height, channels).unwrap().2;
+        assert_eq!(to_interleaved(&planar, width, height, channels), Ok((width,height,buf.clone())));
+
+        for angle in [0.0, 0.4, std::f64::consts::FRAC_PI_2, 2.5, -1.0] {
+            let (new_width, new_height, rotated) = fast_rotate(&buf, &[1,2,3,4], channels, width, height, angle).unwrap();
+
+            assert_eq!(fast_rotate_with_layout(&planar, &[1,2,3,4], channels, width, height, angle, PixelLayout::Planar), to_planar(&rotated, new_width, new_height, channels), "angle {}", angle);
+        }
+    }
+}
@@ -1,33 +1,426 @@
-use std::collections::HashMap;
 use thiserror::Error;
 
+// Diagnostics are only emitted through the log crate when the debug-logging feature is enabled,
+// otherwise the arguments are still type checked but never evaluated
+#[cfg(feature = "debug-logging")]
+macro_rules! debug {
+    ($($arg:tt)*) => { log::debug!($($arg)*) }
+}
+
+#[cfg(not(feature = "debug-logging"))]
+macro_rules! debug {
+    ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } }
+}
+
+#[cfg(feature = "debug-logging")]
+macro_rules! trace {
+    ($($arg:tt)*) => { log::trace!($($arg)*) }
+}
+
+#[cfg(not(feature = "debug-logging"))]
+macro_rules! trace {
+    ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } }
+}
+
+mod alpha;
+#[cfg(feature = "gif")]
+mod animation;
+mod atlas;
+mod blur;
+mod buffer;
+mod color;
+mod crop;
+mod dynamic;
+#[cfg(feature = "image-interop")]
+mod interop;
+mod layout;
+mod orientation;
+mod outline;
+mod prepared;
+#[cfg(feature = "serde")]
+mod rig;
+mod scale;
+mod shadow;
+mod transform;
+mod warp;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use alpha::{add_alpha_mask, add_opaque_alpha, premultiply, unpremultiply};
+#[cfg(feature = "gif")]
+pub use animation::{encode_gif, AnimationError};
+pub use atlas::{pack_atlas, pack_grid, pad_frames};
+pub use blur::box_blur;
+pub use buffer::{get_pixel, set_pixel, Image, ImageView};
+pub use color::{distinct_colors, flood_fill, map_colors, palette, palette_swap, tint};
+pub use crop::{content_bounds, crop, pad_constant, pad_reflect, trim};
+pub use dynamic::DynImage;
+pub use layout::{fast_rotate_with_layout, to_interleaved, to_planar, PixelLayout};
+pub use orientation::{flip_horizontal, flip_vertical, rotate_180, rotate_270, rotate_90, transpose};
+pub use outline::outline;
+pub use prepared::{prepare_appendage, stitch_with_prepared, PreparedAppendage};
+#[cfg(feature = "serde")]
+pub use rig::{Easing, MetadataError, Placement, RigMetadata};
+pub use scale::{downscale, downscale_average, downscale_average_premultiplied, downscale_keyed, downscale_with_filter, resize_to, scale_nearest, thumbnail, upscale, upscale3x, DownscaleFilter};
+pub use shadow::drop_shadow;
+pub use transform::Transform;
+pub use warp::{shear, warp_affine, warp_perspective, Sampling, MAX_WARP_DIMENSION};
+
+use scale::ModeFilter;
+
 #[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
 /// The Types of Errors that can occur when reading a buffer in rotation/stitching actions
+///
+/// New kinds of errors may be added in minor releases, so matches on it need a wildcard arm
 pub enum ParsingError {
     #[error("Buffer is empty")]
     /// The image buffer passed contains no data
     EmptyBufferError,
-    #[error("Buffer is incomplete")]
+    #[error("Buffer is incomplete, expected {expected} values but got {actual}")]
     /// The length image buffer passed does not match the dimensions given. 
     /// 
     /// length of buffer != width\*height\*channels
-    BufferSizeMismatch
+    BufferSizeMismatch {
+        /// The length the buffer should have
+        expected: usize,
+        /// The length of the buffer passed
+        actual: usize
+    },
+    #[error("Images have differing channel counts")]
+    /// Two images that are combined have a different number of channels per pixel
+    ChannelMismatch,
+    #[error("Dimensions are too large")]
+    /// The dimensions cannot be represented by the integer type they are being converted to
+    DimensionsTooLarge,
+    #[error("Anchor lies outside the image")]
+    /// An anchor point does not lie within the dimensions of its image
+    AnchorOutOfBounds,
+    #[error("Empty pixel has {actual} channels but the image has {expected}")]
+    /// The empty pixel passed does not have one value per channel
+    ///
+    /// length of empty != channels
+    EmptyPixelMismatch {
+        /// The no. of channels of the image
+        expected: usize,
+        /// The length of the empty pixel passed
+        actual: usize
+    },
+    #[error("Pixels must have at least 1 channel")]
+    /// The number of channels per pixel passed was 0
+    ZeroChannels,
+    #[error("Alpha channel index is out of bounds")]
+    /// The alpha channel index given is not less than the number of channels per pixel
+    AlphaIndexOutOfBounds,
+    #[error("Image lies entirely outside the destination")]
+    /// The image being composited does not overlap the destination at all at the given offset
+    NoOverlap,
+    #[error("Opacity must be between 0 and 1")]
+    /// The opacity passed is not within 0.0..=1.0
+    OpacityOutOfRange,
+    #[error("Color key has {actual} channels but the image has {expected}")]
+    /// The color key passed does not have one value per channel
+    ///
+    /// length of color key != channels
+    ColorKeyMismatch {
+        /// The no. of channels of the image
+        expected: usize,
+        /// The length of the color key passed
+        actual: usize
+    },
+    #[error("Window lies outside the image")]
+    /// The window to crop does not fit inside the image
+    WindowOutOfBounds,
+    #[error("Transform matrix cannot be inverted")]
+    /// The transform matrix passed has a determinant of 0 or contains values that are not finite
+    SingularMatrix,
+    #[error("Scale factor must be at least 1")]
+    /// A scale factor or target dimension of 0 was passed
+    ZeroScale,
+    #[error("Pixel lies outside the image")]
+    /// The coordinates of a pixel do not lie within the dimensions of its image
+    PixelOutOfBounds,
+    #[error("Frame is wider than the atlas")]
+    /// A frame passed to pack_atlas() is wider than the maximum width of the atlas
+    FrameTooWide,
+    #[error("Color ramp is empty")]
+    /// The color ramp passed to map_colors() contains no colors
+    EmptyRamp,
+    #[error("Images have differing dimensions, expected {expected:?} but got {actual:?}")]
+    /// Images that are combined pixel by pixel do not have the same (Width, Height)
+    DimensionMismatch {
+        /// The dimensions of the first image
+        expected: (usize,usize),
+        /// The dimensions of the image that differs
+        actual: (usize,usize)
+    },
+    #[error("Images have differing bit depths")]
+    /// Two DynImages that are combined have different subpixel types, they have to be converted explicitly first
+    DepthMismatch
 }
 
 /// The Ordering for which image ends up on top
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum StitchingOrder {
     /// Keep the "source" (unrotated image) on top
     SourceOnTop,
     /// Keep the appendage (rotated image) on top
-    AppendageOnTop
+    AppendageOnTop,
+    /// Neither image is on top, where both have color each contributes in proportion to its alpha.
+    ///
+    /// The combined alpha is the same as with the other orders, but the color doesn't depend on which image is listed first:
+    /// two 50% alpha pixels mix evenly into a 75% alpha pixel, where SourceOnTop or AppendageOnTop would favour the upper one.
+    /// Two opaque pixels are averaged instead of one hiding the other. The blend mode and gamma correction options are not used
+    Blend
 }
 
 /// Determines the Algorithm used for rotation before stitching
+///
+/// The fancy levels upscale the image before rotating it and scale it back down afterwards.
+/// Each level doubles the upscaling, which keeps more detail intact but takes roughly 4 times as long
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum StitchingQuality {
     /// Equivalent to fast_rotate()
     Fast,
-    /// Equivalent to fancy_rotate()
-    Fancy
+    /// Equivalent to fancy_rotate(), the same as Fancy8x
+    Fancy,
+    /// Fancy rotation with a single upscale pass, the quickest but roughest of the fancy levels
+    Fancy2x,
+    /// Fancy rotation with two upscale passes
+    Fancy4x,
+    /// Fancy rotation with three upscale passes, the highest quality
+    Fancy8x
+}
+
+impl StitchingQuality {
+    /// How much a fancy level upscales the image before rotating it, None for StitchingQuality::Fast
+    pub(crate) fn supersample_factor(&self) -> Option<usize> {
+        match self {
+            StitchingQuality::Fast => None,
+            StitchingQuality::Fancy2x => Some(2),
+            StitchingQuality::Fancy4x => Some(4),
+            StitchingQuality::Fancy | StitchingQuality::Fancy8x => Some(8)
+        }
+    }
+}
+
+/// Determines whether rotation is allowed to introduce new colors
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RotationMode {
+    /// Only ever copies pixels of the source image, keeping the palette of pixel art intact
+    #[default]
+    PixelArt,
+    /// Interpolates between pixels, equivalent to smooth_rotate().
+    /// Gives anti-aliased edges for photographic or painted images, but introduces new colors
+    Smooth
+}
+
+/// Determines how the pixels of the top image are combined with the ones beneath them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode {
+    /// Regular alpha compositing, the top image is laid over the bottom one
+    SourceOver,
+    /// Multiplies the colors together, which can only darken
+    Multiply,
+    /// Inverse of Multiply, which can only lighten
+    Screen,
+    /// Adds the colors together, clamping at the maximum value of the subpixel type
+    Additive,
+    /// The top pixel overwrites the bottom one wherever its alpha is not zero
+    Replace
+}
+
+/// A point of stitching on an image
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Anchor {
+    /// An exact pixel (x,y)
+    Pixel((usize,usize)),
+    /// A position relative to the dimensions of the image, (0.0,0.0) is the top left pixel and (1.0,1.0) the bottom right one.
+    /// 
+    /// Useful for rigs that are reused across sprites of different resolutions
+    Relative((f64,f64))
+}
+
+impl Anchor {
+    /// Resolves the anchor to a pixel of an image of the given dimensions
+    /// 
+    /// Returns ParsingError::AnchorOutOfBounds if the pixel lies outside the image or a relative coordinate is not within 0.0..=1.0
+    pub fn to_pixel(&self, dimensions: (usize,usize)) -> Result<(usize,usize),ParsingError> {
+        let pixel = match *self {
+            Anchor::Pixel(pixel) => pixel,
+            Anchor::Relative((x, y)) => {
+                if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+                    return Err(ParsingError::AnchorOutOfBounds)
+                }
+
+                // 1.0 would be just past the last pixel, so it is clamped onto it
+                let to_pixel = |relative: f64, length: usize| usize::min((relative*length as f64) as usize, length.saturating_sub(1));
+                (to_pixel(x, dimensions.0), to_pixel(y, dimensions.1))
+            }
+        };
+
+        check_anchor(pixel, dimensions)?;
+        Ok(pixel)
+    }
+}
+
+impl From<(usize,usize)> for Anchor {
+    fn from(pixel: (usize,usize)) -> Anchor {
+        Anchor::Pixel(pixel)
+    }
+}
+
+/// Where an appendage is attached to a source image, at what angle and which of the two ends up on top
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Joint {
+    /// Point of stitching on the source image
+    pub src_anchor: Anchor,
+    /// Desired angle of the stitched appendage (in radians)
+    pub src_angle: f64,
+    /// Point of stitching on the appendage image
+    pub appendage_anchor: Anchor,
+    /// Current angle of the appendage in its image (in radians)
+    pub appendage_angle: f64,
+    /// Whether the appendage is drawn above or below the source
+    pub order: StitchingOrder
+}
+
+impl Joint {
+    /// Creates a Joint, the anchors can be given either as pixels or relative to the dimensions of their image, see Anchor
+    pub fn new(src_anchor: impl Into<Anchor>, src_angle: f64, appendage_anchor: impl Into<Anchor>, appendage_angle: f64, order: StitchingOrder) -> Joint {
+        Joint { src_anchor: src_anchor.into(), src_angle, appendage_anchor: appendage_anchor.into(), appendage_angle, order }
+    }
+}
+
+/// Which channel of each pixel holds its alpha value
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaChannel {
+    /// The last channel, as in RGBA or BGRA
+    Last,
+    /// The channel at the given index, e.g. 0 for ARGB
+    Index(usize),
+    /// The pixels have no alpha channel, the top image simply replaces whatever is beneath it.
+    /// When stitching, canvas pixels equal to the empty pixel count as uncovered, so the appendage shows there even beneath the source
+    Opaque
+}
+
+impl AlphaChannel {
+    /// Resolves the index of the alpha channel in a pixel of the given number of channels, None if the pixels are opaque
+    pub(crate) fn index(&self, channels: usize) -> Result<Option<usize>, ParsingError> {
+        match *self {
+            AlphaChannel::Last => Ok(channels.checked_sub(1)),
+            AlphaChannel::Index(index) if index < channels => Ok(Some(index)),
+            AlphaChannel::Index(_) => Err(ParsingError::AlphaIndexOutOfBounds),
+            AlphaChannel::Opaque => Ok(None)
+        }
+    }
+}
+
+/// What a rotation fills in where the canvas maps outside the source image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeMode<'a, T> {
+    /// Fill with the given empty pixel, the same as fast_rotate()
+    Fill(&'a [T]),
+    /// Repeat the closest pixel on the border of the image
+    Clamp,
+    /// Tile the image, so the pixels past the right edge come from the left edge and so on
+    Wrap,
+    /// Tile mirrored copies of the image, so the edges line up without a seam
+    Reflect
+}
+
+impl<T> EdgeMode<'_, T> {
+    /// Maps a pixel coordinate that may lie outside 0..length back into it
+    fn resolve(&self, coordinate: isize, length: usize) -> usize {
+        let length = length as isize;
+
+        let resolved = match self {
+            EdgeMode::Fill(_) | EdgeMode::Clamp => coordinate.clamp(0, length-1),
+            EdgeMode::Wrap => coordinate.rem_euclid(length),
+            EdgeMode::Reflect => {
+                let mirrored = coordinate.rem_euclid(2*length);
+                if mirrored < length { mirrored } else { 2*length-1-mirrored }
+            }
+        };
+
+        resolved as usize
+    }
+}
+
+/// Determines how the position a pixel of the rotated image maps back to in the source is snapped onto a source pixel
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RoundingPolicy {
+    /// Takes the closest source pixel, which keeps the rotated image centred
+    #[default]
+    Round,
+    /// Takes the source pixel towards the top left (before rotation), shifting the image by up to a pixel towards the bottom right.
+    /// Edges that are cut on one side of the image grow on the other
+    Floor,
+    /// Takes the source pixel towards the bottom right (before rotation), the mirror image of Floor
+    Ceil
+}
+
+impl RoundingPolicy {
+    /// Snaps a position onto a whole pixel
+    fn snap(&self, position: f64) -> f64 {
+        match self {
+            RoundingPolicy::Round => position.round(),
+            RoundingPolicy::Floor => position.floor(),
+            RoundingPolicy::Ceil => position.ceil()
+        }
+    }
+}
+
+/// Additional options for stitch_with_config() and stitch_all()
+#[derive(Clone, Debug, PartialEq)]
+pub struct StitchConfig<'a, T> {
+    /// How the image on top is combined with the one beneath it, defaults to BlendMode::SourceOver
+    pub blend_mode: BlendMode,
+    /// Which channel holds the alpha value, defaults to AlphaChannel::Last
+    pub alpha_channel: AlphaChannel,
+    /// Blend colors in linear light instead of sRGB, which keeps soft edges from darkening, defaults to false
+    /// 
+    /// Only the color channels are converted, alpha is left as is
+    pub gamma_correct: bool,
+    /// Multiplies the alpha of the appendage, must be within 0.0..=1.0, defaults to 1.0
+    /// 
+    /// Applies whether the appendage is above or below the source, has no effect with AlphaChannel::Opaque
+    pub opacity: f32,
+    /// Pixels exactly equal to this color are treated as transparent, for images that use a color key instead of an alpha channel.
+    /// 
+    /// They are left out when blending and when picking the most common pixel during fancy rotation, defaults to None
+    pub color_key: Option<&'a [T]>,
+    /// With RotationMode::Smooth, the fancy levels of StitchingQuality rotate the appendage with smooth_rotate() instead of fancy_rotate(),
+    /// defaults to RotationMode::PixelArt
+    ///
+    /// Has no effect on StitchingQuality::Fast or on stitch_with_prepared(), which is always PixelArt
+    pub rotation_mode: RotationMode,
+    /// Keep the result the same size as the source, cutting off any part of the appendage that sticks out of it, defaults to false
+    ///
+    /// Useful when the source is a fixed size frame of an animation
+    pub clip_to_source: bool,
+    /// How the channels of the source, the appendage and the result are arranged, defaults to PixelLayout::Interleaved.
+    /// The empty pixel and color key are always a single pixel with one value per channel
+    ///
+    /// Blending works on whole pixels, so planar images are interleaved for the stitch and the result is split back into planes.
+    /// Only used by stitch_with_config()
+    pub layout: PixelLayout
+}
+
+impl<'a, T> Default for StitchConfig<'a, T> {
+    fn default() -> StitchConfig<'a, T> {
+        StitchConfig {
+            blend_mode: BlendMode::SourceOver,
+            alpha_channel: AlphaChannel::Last,
+            gamma_correct: false,
+            opacity: 1.0,
+            color_key: None,
+            rotation_mode: RotationMode::PixelArt,
+            clip_to_source: false,
+            layout: PixelLayout::Interleaved
+        }
+    }
 }
 
 /// A Trait that designates valid subpixel types for stitching operations
@@ -36,41 +429,166 @@ pub enum StitchingQuality {
 pub trait StitchableType: Clone + Copy + TryFrom<i32> {
     /// Returns The Maximum value of this type
     fn maxvalue() -> Self;
+    /// Converts the value to an f64 for blending math
+    fn to_f64(self) -> f64;
+    /// Converts an f64 back to this type, rounding and clamping it into range
+    fn from_f64(value: f64) -> Self;
+    /// Packs a whole pixel into a u32 that is equal for two pixels exactly when they are, None if it does not fit.
+    /// The mode based downscale of the fancy algorithm counts packed pixels much faster than slices
+    fn pack_pixel(_pixel: &[Self]) -> Option<u32> {
+        None
+    }
 }
 
 impl StitchableType for u8 {
     fn maxvalue() -> u8 {
         std::u8::MAX
     }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> u8 {
+        value.round() as u8
+    }
+
+    fn pack_pixel(pixel: &[u8]) -> Option<u32> {
+        <[u8; 4]>::try_from(pixel).ok().map(u32::from_be_bytes)
+    }
 }   
 
 impl StitchableType for u16 {
     fn maxvalue() -> u16 {
         std::u16::MAX
     }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> u16 {
+        value.round() as u16
+    }
 }   
 
 impl StitchableType for u32 {
     fn maxvalue() -> u32 {
         std::u32::MAX
     }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> u32 {
+        value.round() as u32
+    }
 }   
 
 impl StitchableType for u64 {
     fn maxvalue() -> u64 {
         std::u64::MAX
     }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> u64 {
+        value.round() as u64
+    }
 }
 
 impl StitchableType for u128 {
     fn maxvalue() -> u128 {
         std::u128::MAX
     }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> u128 {
+        value.round() as u128
+    }
 }
 
 /// (Width, Height, Image buffer) or ParsingError
 pub type StitchingResult<T> = Result<(usize,usize,Vec<T>),ParsingError>;
 
+/// (Width, Height, Image buffer) along with a position in the image or ParsingError,
+/// each function documents which point the position marks, e.g. where the anchor ended up after stitching
+pub type PositionedResult<T, P = (usize,usize)> = Result<((usize,usize,Vec<T>),P),ParsingError>;
+
+/// (Width, Height, Image buffer) along with a single channel mask of the same dimensions or ParsingError
+pub type MaskedResult<T> = Result<((usize,usize,Vec<T>),Vec<u8>),ParsingError>;
+
+/// A sheet of frames along with the rectangle (x, y, width, height) of each frame in it or ParsingError
+pub type AtlasResult<T> = Result<(Image<T>,Vec<(usize,usize,usize,usize)>),ParsingError>;
+
+/// Checks that a buffer holds exactly one image of the given dimensions
+pub(crate) fn check_buffer<T>(buf: &[T], channels: usize, width: usize, height: usize) -> Result<(),ParsingError> {
+    if channels == 0 {
+        return Err(ParsingError::ZeroChannels)
+    }
+    if buf.is_empty() {
+        return Err(ParsingError::EmptyBufferError)
+    }
+    let expected = buffer_len(width, height, channels)?;
+    if buf.len() != expected {
+        return Err(ParsingError::BufferSizeMismatch { expected, actual: buf.len() })
+    }
+
+    Ok(())
+}
+
+/// Computes width\*height\*channels, the length of an image buffer,
+/// returning ParsingError::DimensionsTooLarge if it overflows or could never be allocated (more than isize::MAX)
+pub(crate) fn buffer_len(width: usize, height: usize, channels: usize) -> Result<usize,ParsingError> {
+    width.checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .filter(|len| *len <= isize::MAX as usize)
+        .ok_or(ParsingError::DimensionsTooLarge)
+}
+
+/// Creates a buffer of the given number of pixels, all equal to pixel
+pub(crate) fn filled_canvas<T: Clone>(pixel: &[T], pixels: usize) -> Vec<T> {
+    let mut canvas = Vec::with_capacity(pixels*pixel.len());
+
+    for _ in 0..pixels {
+        canvas.extend_from_slice(pixel);
+    }
+
+    canvas
+}
+
+/// Checks that the empty pixel has one value per channel
+pub(crate) fn check_empty<T>(empty: &[T], channels: usize) -> Result<(),ParsingError> {
+    if empty.len() != channels {
+        return Err(ParsingError::EmptyPixelMismatch { expected: channels, actual: empty.len() })
+    }
+
+    Ok(())
+}
+
+/// Checks that the color key, if there is one, has one value per channel
+pub(crate) fn check_color_key<T>(color_key: Option<&[T]>, channels: usize) -> Result<(),ParsingError> {
+    match color_key {
+        Some(key) if key.len() != channels => Err(ParsingError::ColorKeyMismatch { expected: channels, actual: key.len() }),
+        _ => Ok(())
+    }
+}
+
+/// Checks that an anchor point lies within an image of the given dimensions
+pub(crate) fn check_anchor(anchor: (usize,usize), dimensions: (usize,usize)) -> Result<(),ParsingError> {
+    if anchor.0 >= dimensions.0 || anchor.1 >= dimensions.1 {
+        return Err(ParsingError::AnchorOutOfBounds)
+    }
+
+    Ok(())
+}
+
 /// Rotates an image using the fancy algorithm.
 /// Wont introduce any new colors as there is no color interpolation 
 /// 
@@ -78,7 +596,7 @@ pub type StitchingResult<T> = Result<(usize,usize,Vec<T>),ParsingError>;
 /// 
 /// - buf - The image
 /// - width, height - Dimensions of image
-/// - channels - No. of channels per pixel
+/// - channels - No. of channels per pixel, must be at least 1
 /// - empty - Empty space will be filled with this value
 /// - angle - The angle of rotation (in radians), 
 ///   - positive => Anticlockwise, 
@@ -86,436 +604,2089 @@ pub type StitchingResult<T> = Result<(usize,usize,Vec<T>),ParsingError>;
 /// 
 /// use fast_rotate() for faster rotation
 pub fn fancy_rotate<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64) -> StitchingResult<T> where [T]: Eq + std::hash::Hash{
-    
-    let image2x = upscale(&buf, channels, width, height);
-    let image4x = upscale(&image2x.2.as_slice(), channels,image2x.0, image2x.1);
-    let image8x = upscale(&image4x.2.as_slice(), channels,image4x.0, image4x.1);
+    fancy_rotate_keyed(buf, empty, channels, width, height, angle, None)
+}
+
+/// Same as fancy_rotate() but pixels equal to color_key are treated as transparent when the image is scaled back down,
+/// so they don't eat into the edges of the sprite. Pass the key as empty too to fill the revealed corners with it
+pub fn fancy_rotate_keyed<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64, color_key: Option<&[T]>) -> StitchingResult<T> where [T]: Eq + std::hash::Hash{
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+    check_color_key(color_key, channels)?;
 
-    let image_rotated = fast_rotate(image8x.2.as_slice(), empty, channels, image8x.0, image8x.1, angle)?;
+    if quarter_turns(angle) == Some(0) {
+        return Ok((width,height,buf.to_vec()))
+    }
 
-    let downscaled = downscale(image_rotated.2.as_slice(), channels, image_rotated.0, image_rotated.1, 8);
+    let image8x = supersample(buf, channels, width, height, 8)?;
 
-    Ok(downscaled)
+    rotate_supersampled(&image8x, empty, channels, &Rotation::new(angle), 8, ModeFilter::generic(color_key))
 }
 
-/// Rotates an image using the fast algorithm, result may be noisy for low resolution images. 
-/// Wont introduce any new colors as there is no color interpolation 
-/// 
+/// Rotates an image with the algorithm chosen by quality, see fast_rotate() and fancy_rotate()
+///
 /// # Arguments
-/// 
+///
 /// - buf - The image
 /// - width, height - Dimensions of image
-/// - channels - No. of channels per pixel
+/// - channels - No. of channels per pixel, must be at least 1
 /// - empty - Empty space will be filled with this value
-/// - angle - The angle of rotation (in radians), 
-///   - positive => Anticlockwise, 
+/// - angle - The angle of rotation (in radians),
+///   - positive => Anticlockwise,
 ///   - negative => Clockwise
-/// 
-/// use fancy_rotate() for higher quality rotation
-pub fn fast_rotate<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64) -> StitchingResult<T> {
-    if buf.is_empty() {
-        return Err(ParsingError::EmptyBufferError)
-    }
-    if buf.len() != width*height*channels {
-        return Err(ParsingError::BufferSizeMismatch)
+/// - quality - The rotation algorithm to use, the lower fancy levels trade quality for speed
+pub fn rotate_with_quality<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64, quality: &StitchingQuality) -> StitchingResult<T> where [T]: Eq + std::hash::Hash{
+    rotate_keyed(buf, empty, channels, (width, height), &Rotation::new(angle), quality, ModeFilter::generic(None))
+}
+
+/// Same as rotate_with_quality() but the fancy levels downscale with the given filter, e.g. to treat a color key as transparent (see fancy_rotate_keyed())
+pub(crate) fn rotate_keyed<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, dimensions: (usize,usize), rotation: &Rotation, quality: &StitchingQuality, filter: ModeFilter<T>) -> StitchingResult<T> where [T]: Eq + std::hash::Hash{
+    let (width, height) = dimensions;
+    let factor = match quality.supersample_factor() {
+        Some(factor) => factor,
+        None => return fast_rotate_by(buf, empty, channels, width, height, rotation)
+    };
+
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+
+    // Without any rotation the round trip through supersample() and downscale_keyed() could only lose detail
+    if quarter_turns(rotation.angle) == Some(0) {
+        return Ok((width,height,buf.to_vec()))
     }
 
-    let sin = angle.sin();
-    let cos = angle.cos();
+    let supersampled = supersample(buf, channels, width, height, factor)?;
 
-    let widthf = width as f64;
-    let heightf = height as f64;
+    rotate_supersampled(&supersampled, empty, channels, rotation, factor, filter)
+}
 
-    let diag_length = (widthf.powf(2.0)+heightf.powf(2.0)).sqrt();
-    let diag_angle_with_horizontal = (heightf/widthf).atan();
-    let diag_angle_1 = diag_angle_with_horizontal + angle;
-    let diag_angle_2 = -diag_angle_with_horizontal + angle;
+/// Rotates an image with color interpolation.
+/// Each pixel averages 4x4 bilinear samples of the source, so edges are anti-aliased against the empty pixel
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel, must be at least 1
+/// - empty - Empty space will be filled with this value
+/// - angle - The angle of rotation (in radians),
+///   - positive => Anticlockwise,
+///   - negative => Clockwise
+///
+/// Unlike fancy_rotate() and fast_rotate() this introduces new colors, so it is meant for photographic or painted images rather than pixel art.
+/// The result has the same dimensions as with fast_rotate()
+pub fn smooth_rotate<T: StitchableType>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64) -> StitchingResult<T> {
+    smooth_rotate_by(buf, empty, channels, width, height, &Rotation::new(angle))
+}
 
-    let new_width = (f64::max(diag_angle_1.cos().abs(), diag_angle_2.cos().abs())*diag_length).round();
-    let new_height = (f64::max(diag_angle_1.sin().abs(), diag_angle_2.sin().abs())*diag_length).round();
+/// Same as smooth_rotate() but for a rotation whose sine and cosine have already been computed
+pub(crate) fn smooth_rotate_by<T: StitchableType>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, rotation: &Rotation) -> StitchingResult<T> {
+    const SAMPLES: usize = 4;
 
-    println!("diagonal length {} at angle of diagonals {} & {} gives dimensions {}x{}",diag_length,diag_angle_1,diag_angle_2,new_width,new_height);
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
 
-    let mut result_buffer: Vec<T> = Vec::with_capacity((new_height*new_width) as usize*channels);
-    
-    for y in (0..new_height as u32).map(|x| x as f64) {
-        for x in (0..new_width as u32).map(|x| x as f64) {
+    if let Some(result) = rotate_quarter_turns(buf, channels, width, height, rotation.angle) {
+        return result
+    }
+
+    let (new_width, new_height) = compute_rotated_dims(width, height, rotation.angle);
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(new_width, new_height, channels)?);
+
+    let (sin, cos) = (rotation.sin, rotation.cos);
+    let widthf = width as f64;
+    let heightf = height as f64;
 
-            let pos: [f64; 2] = [x+0.5-(new_width)/2.0,y+0.5-(new_height)/2.0];
+    let mut sample: Vec<T> = empty.to_vec();
+    let mut sums: Vec<f64> = vec![0.0; channels];
 
-            let x_along_oldx: f64 = ((pos[0]*cos - pos[1]*sin)+(widthf/2.0)-0.5).round();
+    for y in 0..new_height {
+        for x in 0..new_width {
+            sums.iter_mut().for_each(|sum| *sum = 0.0);
+
+            for sub_y in 0..SAMPLES {
+                for sub_x in 0..SAMPLES {
+                    let pos: [f64; 2] = [
+                        x as f64+(sub_x as f64+0.5)/SAMPLES as f64-(new_width as f64)/2.0,
+                        y as f64+(sub_y as f64+0.5)/SAMPLES as f64-(new_height as f64)/2.0
+                    ];
+
+                    let source_x = (pos[0]*cos - pos[1]*sin)+(widthf/2.0)-0.5;
+                    let source_y = (pos[0]*sin + pos[1]*cos)+(heightf/2.0)-0.5;
+
+                    // Subsamples are only taken from the image where they lie within it, the rest see the empty pixel
+                    if -0.5 <= source_x && source_x < widthf-0.5 && -0.5 <= source_y && source_y < heightf-0.5 {
+                        warp::sample_bilinear(buf, width, height, channels, source_x, source_y, &mut sample);
+                    } else {
+                        sample.clone_from_slice(empty);
+                    }
 
-            if 0.0 <= x_along_oldx && x_along_oldx < widthf {
-                let y_along_oldy: f64 = ((pos[0]*sin + pos[1]*cos)+(heightf/2.0)-0.5).round();
-                
-                if 0.0 <= y_along_oldy && y_along_oldy < heightf {
-                    let index = ((y_along_oldy*width as f64)+x_along_oldx) as usize*channels;
-                    //println!("Filling pixel {},{} (vec: {:?} )from {},{} index {} which is {:?}",x,y,pos,(pos[0]*cos - pos[1]*sin)+(widthf/2.0),(pos[0]*sin + pos[1]*cos)+(heightf/2.0),index,&buf[index..index+channels as usize]);
-                    result_buffer.extend_from_slice(&buf[index..index+channels]);
-                    continue;
+                    sums.iter_mut().zip(&sample).for_each(|(sum, value)| *sum += value.to_f64());
                 }
             }
-            //println!("Filling pixel {},{} with emptiness",x,y);
-            result_buffer.extend_from_slice(empty); 
-
-            /* let y_along_oldy: f64 = ((pos[0]*sin + pos[1]*cos)+(heightf/2.0)).round();
-            println!("Resolved vector along original axis ({},{})",x_along_oldx,y_along_oldy); // (-1.28,-4.781)
 
-            if 0.0 < x_along_oldx && x_along_oldx < widthf && 0.0 < y_along_oldy && y_along_oldy < heightf {
-                let index = (y_along_oldy*width as f64+x_along_oldx as f64) as usize*channels;
-                println!("Pulling pixel {} which is {:?}",index,&buf[index..index+channels as usize]);
-                result_buffer.extend_from_slice(&buf[index..index+channels as usize])
-            }
-            else {
-                result_buffer.extend_from_slice(empty);
-            } */
+            result_buffer.extend(sums.iter().map(|sum| T::from_f64(sum/(SAMPLES*SAMPLES) as f64)));
         }
     }
 
-    println!("Returning");
-    Ok((new_width as usize,new_height as usize,result_buffer))
+    Ok((new_width,new_height,result_buffer))
 }
 
-/// Stitches Two images together
-/// 
+/// Rotates an image by each of the given angles using the fancy algorithm, e.g. to render the frames of a spinning sprite
+///
 /// # Arguments
-/// 
-/// - src, src_dimensions, src_anchor , src_angle - The source image, dimensions and point of stitching and desired angle of stitched appendage
-/// - appendage, appendage_dimensions, appendage_anchor , appendage_angle - The appendage image, dimensions and point of stitching and its current angle in image
-/// - empty - Equivalent of empty pixel
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
 /// - channels - No. of channels per pixel
-pub fn stitch<T>(src: &[T], appendage: &[T], empty: &[T], channels: usize, src_dimensions: (usize,usize), src_anchor: (usize,usize), src_angle: f64, appendage_dimensions: (usize,usize), appendage_anchor: (usize,usize), appendage_angle: f64, top: StitchingOrder, quality: StitchingQuality) -> StitchingResult<T> 
-where T: StitchableType + std::fmt::Debug, f32: From<T>, [T]: Eq + std::hash::Hash
-{
-    let rotation = src_angle - appendage_angle;
-
-    let rotated = {
-        match quality {
-            StitchingQuality::Fancy => fancy_rotate(appendage, empty, channels, appendage_dimensions.0, appendage_dimensions.1, rotation)?,
-            StitchingQuality::Fast => fast_rotate(appendage, empty, channels, appendage_dimensions.0, appendage_dimensions.1, rotation)?
-        }
-    };
-    let rotated_anchor_pos = rotate_point(appendage_anchor, appendage_dimensions.0, appendage_dimensions.1, rotation);
+/// - empty - Empty space will be filled with this value
+/// - angles - The angles of rotation (in radians), see fancy_rotate()
+///
+/// The image is only upscaled once and reused for every angle, which is much cheaper than calling fancy_rotate() in a loop.
+/// Returns one rotated image per angle, in the same order
+pub fn rotate_sequence<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angles: &[f64]) -> Result<Vec<(usize,usize,Vec<T>)>,ParsingError> where [T]: Eq + std::hash::Hash{
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+
+    let image8x = supersample(buf, channels, width, height, 8)?;
+
+    angles.iter().map(|angle| match quarter_turns(*angle) {
+        Some(0) => Ok((width,height,buf.to_vec())),
+        _ => rotate_supersampled(&image8x, empty, channels, &Rotation::new(*angle), 8, ModeFilter::generic(None))
+    }).collect()
+}
 
-    // T, R, B, L
-    let src_dist: [usize; 4] = [
-        src_anchor.1,
-        src_dimensions.0-src_anchor.0,
-        src_dimensions.1-src_anchor.1,
-        src_anchor.0
-    ];
+/// Upscales an image by factor (2, 4 or 8) with that many passes of upscale(), the first step of the fancy algorithm
+pub(crate) fn supersample<T: Clone + std::fmt::Debug>(buf: &[T], channels: usize, width: usize, height: usize, factor: usize) -> StitchingResult<T> where [T]: Eq {
+    let passes = factor.trailing_zeros();
+    let len = buffer_len(width, height, channels)?;
 
-    let rot_dist: [usize; 4] = [
-        rotated_anchor_pos.1,
-        rotated.0-rotated_anchor_pos.0,
-        rotated.1-rotated_anchor_pos.1,
-        rotated_anchor_pos.0
+    // The passes ping-pong between two buffers, the first one is allocated at its final size up front so it never grows
+    let mut buffers = [
+        Vec::with_capacity(len.checked_mul(factor*factor).ok_or(ParsingError::DimensionsTooLarge)?),
+        Vec::with_capacity(len.checked_mul(factor*factor/4).ok_or(ParsingError::DimensionsTooLarge)?)
     ];
+    let mut dimensions = (width, height);
 
-    let dist: Vec<usize> = src_dist.iter().zip(rot_dist.iter()).map(|(x, y)| usize::max(*x,*y)).collect();
-    
-    println!("Post rotation anchor is at {},{} in an image of {},{}",rotated_anchor_pos.0,rotated_anchor_pos.1,rotated.0,rotated.1);
-
-    let width = dist[1] + dist[3];
-    let height = dist[0] + dist[2];
-
-    let rotated_image_topleft = (dist[3]-rotated_anchor_pos.0,dist[0]-rotated_anchor_pos.1);
-    let src_image_topleft = (dist[3]-src_anchor.0,dist[0]-src_anchor.1);
-
-    let mut res = empty.to_vec().repeat(height*width*channels);
+    for pass in 0..passes {
+        // The last pass always writes into the first buffer
+        let [first, second] = &mut buffers;
+        let (source, target) = if (passes-1-pass)%2 == 0 { (&*second, first) } else { (&*first, second) };
+        let source: &[T] = if pass == 0 { buf } else { source };
 
-    println!("Toplefts are at {},{} and {},{}",rotated_image_topleft.0,rotated_image_topleft.1,src_image_topleft.0,src_image_topleft.1);
-
-    for y in 0..src_dimensions.1 {
-        for x in 0..src_dimensions.0 {
-            for c in 0..channels {
-                res[(src_image_topleft.1+y)*width*channels+(src_image_topleft.0+x)*channels+c] = src[y*src_dimensions.0*channels+x*channels+c];
-            }
-        }
+        dimensions = scale::upscale_into(source, dimensions.0, dimensions.1, channels, target)?;
     }
 
-    match top{
-        StitchingOrder::AppendageOnTop => {
-            for y in 0..rotated.1 {
-                for x in 0..rotated.0 {
-                    //println!("Pulled pixel {:?} for {},{} Had index {}",&rotated.2[y*rotated.0*channels+x*channels..y*rotated.0*channels+x*channels+channels],x,y,y*rotated.0*channels+x*channels);
-                    let blended = blend(&rotated.2[y*rotated.0*channels+x*channels..y*rotated.0*channels+x*channels+channels], &res[(rotated_image_topleft.1+y)*width*channels+(rotated_image_topleft.0+x)*channels..(rotated_image_topleft.1+y)*width*channels+(rotated_image_topleft.0+x)*channels+channels]);
-                    for c in 0..channels {
-                        res[(rotated_image_topleft.1+y)*width*channels+(rotated_image_topleft.0+x)*channels+c] = blended[c]
-                    }
-                }
-            }
-        },
-        StitchingOrder::SourceOnTop => {
-            for y in 0..rotated.1 {
-                for x in 0..rotated.0 {
-                    let blended = blend(&res[(rotated_image_topleft.1+y)*width*channels+(rotated_image_topleft.0+x)*channels..(rotated_image_topleft.1+y)*width*channels+(rotated_image_topleft.0+x)*channels+channels], &rotated.2[y*rotated.0*channels+x*channels..y*rotated.0*channels+x*channels+channels]);
-                    for c in 0..channels {
-                        res[(rotated_image_topleft.1+y)*width*channels+(rotated_image_topleft.0+x)*channels+c] = blended[c]
-                    }
-                }
-            }
-        }
-    };
-        
-
-    Ok((width,height,res))
+    let [supersampled, _] = buffers;
+    Ok((dimensions.0,dimensions.1,supersampled))
 }
 
-fn rotate_point(point: (usize,usize), width: usize, height: usize, angle: f64) -> (usize,usize) {
-    let sin = angle.sin();
-    let cos = angle.cos();
+/// Rotates an image upscaled by supersample() and scales it back down by the same factor, the rest of the fancy algorithm
+pub(crate) fn rotate_supersampled<T: Clone + std::fmt::Debug>(supersampled: &(usize,usize,Vec<T>), empty: &[T], channels: usize, rotation: &Rotation, factor: usize, filter: ModeFilter<T>) -> StitchingResult<T> where [T]: Eq + std::hash::Hash{
+    let image_rotated = fast_rotate_by(supersampled.2.as_slice(), empty, channels, supersampled.0, supersampled.1, rotation)?;
 
-    let widthf = width as f64;
-    let heightf = height as f64;
+    scale::mode_blocks(image_rotated.2.as_slice(), image_rotated.0, image_rotated.1, channels, factor, filter)
+}
 
-    let diag_length = (widthf.powf(2.0)+heightf.powf(2.0)).sqrt();
-    let diag_angle_with_horizontal = (heightf/widthf).atan();
-    let diag_angle_1 = diag_angle_with_horizontal + angle;
-    let diag_angle_2 = -diag_angle_with_horizontal + angle;
+/// Rotates an image using the fast algorithm, result may be noisy for low resolution images. 
+/// Wont introduce any new colors as there is no color interpolation 
+/// 
+/// # Arguments
+/// 
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel, must be at least 1
+/// - empty - Empty space will be filled with this value
+/// - angle - The angle of rotation (in radians), 
+///   - positive => Anticlockwise, 
+///   - negative => Clockwise
+/// 
+/// use fancy_rotate() for higher quality rotation
+pub fn fast_rotate<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64) -> StitchingResult<T> {
+    fast_rotate_by(buf, empty, channels, width, height, &Rotation::new(angle))
+}
 
-    let new_width = (f64::max(diag_angle_1.cos().abs(), diag_angle_2.cos().abs())*diag_length).round();
-    let new_height = (f64::max(diag_angle_1.sin().abs(), diag_angle_2.sin().abs())*diag_length).round();
-    
-    println!("Rotate Point:  dims {}x{}",new_width,new_height);
+/// Same as fast_rotate() but for a rotation whose sine and cosine have already been computed,
+/// so the caller can map points with the exact same ones
+pub(crate) fn fast_rotate_by<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, rotation: &Rotation) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
 
-    let pos_vector = (
-        point.0 as f64 - widthf/2.0 + 0.5,
-        point.1 as f64 - heightf/2.0 + 0.5,
-    );
+    if let Some(result) = rotate_quarter_turns(buf, channels, width, height, rotation.angle) {
+        return result
+    }
 
-    let point_rotated_wrt_center = (
-        pos_vector.0*cos + pos_vector.1*sin,
-        pos_vector.1*cos - pos_vector.0*sin
-    );
+    let (new_width, new_height) = compute_rotated_dims(width, height, rotation.angle);
+    buffer_len(new_width, new_height, channels)?;
 
-    let final_point = (
-        (point_rotated_wrt_center.0 + new_width/2.0) as usize,
-        (point_rotated_wrt_center.1 + new_height/2.0) as usize
-    );
+    debug!("Rotating {}x{} by {} gives dimensions {}x{}",width,height,rotation.angle,new_width,new_height);
 
-    println!("Rotate Point: vec {:?} rotated to {:?} resolved as {:?}",pos_vector,point_rotated_wrt_center,final_point);
+    let result_buffer = sample_rotated(buf, empty, channels, &RotatedCanvas::new((width, height), (new_width, new_height), rotation, RoundingPolicy::Round));
 
-    final_point
+    debug!("Returning");
+    Ok((new_width,new_height,result_buffer))
 }
 
-fn blend<T>(top: &[T], bottom: &[T]) -> Vec<T>
-where T: Copy + Clone + TryFrom<i32> + StitchableType, f32: From<T>
-{
-    let alpha= f32::from(top.last().expect("Pixel cannot have 0 channels").clone())/f32::from(T::maxvalue());
-    let alphacomp = 1.0-alpha;
-    let finalalpha = if alpha > 0.5 { top[top.len()-1] } else { bottom[top.len()-1] };
-    let mut res: Vec<T> = Vec::with_capacity(top.len());
-
-    for i in 0..top.len()-1 {
-        let average: f32 = ( f32::from(top[i].clone())* alpha + f32::from(bottom[i].clone()) * alphacomp).round();
-        let intaverage: i32 = average as i32;
-        res.push(T::try_from(intaverage).unwrap_or(T::maxvalue()))
+/// Same as fast_rotate() but the result is written into a buffer supplied by the caller, e.g. to reuse one allocation
+/// when rotating a sprite every frame
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - empty - Empty space will be filled with this value
+/// - channels - No. of channels per pixel
+/// - width, height - Dimensions of image
+/// - angle - The angle of rotation (in radians),
+///   - positive => Anticlockwise,
+///   - negative => Clockwise
+/// - out - Replaced with the rotated image. It only reallocates if the result is larger than its capacity,
+///   which stops happening once it has held the largest rotation, e.g. the one at 45 degrees for a square sprite
+///
+/// Exact quarter turns still go through rotate_90() and co, which allocate their own buffer before it is copied into out.
+/// Returns the (Width, Height) of the rotated image. If there is an error out is left as is
+pub fn rotate_into<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64, out: &mut Vec<T>) -> Result<(usize,usize),ParsingError> {
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+    let rotation = Rotation::new(angle);
+
+    if let Some(result) = rotate_quarter_turns(buf, channels, width, height, rotation.angle) {
+        let (new_width, new_height, result_buffer) = result?;
+        out.clear();
+        out.extend_from_slice(&result_buffer);
+        return Ok((new_width,new_height))
     }
 
-    res.push(finalalpha);
+    let (new_width, new_height) = compute_rotated_dims(width, height, rotation.angle);
+    buffer_len(new_width, new_height, channels)?;
 
-    res
-}
+    sample_rotated_into(buf, empty, channels, &RotatedCanvas::new((width, height), (new_width, new_height), &rotation, RoundingPolicy::Round), out);
 
-fn downscale<T: Clone>(buf: &[T], channels: usize, width: usize, height: usize, factor: usize) -> (usize,usize,Vec<T>) where [T]: Eq + std::hash::Hash + std::fmt::Debug {
-    let new_width = (width as f64/factor as f64).floor() as usize;
-    let new_height = (height as f64/factor as f64).floor() as usize;
-
-    let mut result_buffer: Vec<T> = Vec::with_capacity((new_height*new_width)*channels as usize);
+    Ok((new_width,new_height))
+}
+
+/// Same as fast_rotate() but rounding decides how the positions that pixels map back to in the source are snapped onto source pixels
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - empty - Empty space will be filled with this value
+/// - channels - No. of channels per pixel
+/// - width, height - Dimensions of image
+/// - angle - The angle of rotation (in radians),
+///   - positive => Anticlockwise,
+///   - negative => Clockwise
+/// - rounding - How positions are snapped, RoundingPolicy::Round is the same as fast_rotate()
+///
+/// The dimensions of the result are the same with every policy.
+/// Round can leave a seam or drop a column where a position lands right between two pixels, when the pieces of a tiled image
+/// are rotated separately Floor or Ceil make every piece snap the same way so their edges line up
+pub fn fast_rotate_with_rounding<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64, rounding: RoundingPolicy) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+    let rotation = Rotation::new(angle);
+
+    if let Some(result) = rotate_quarter_turns(buf, channels, width, height, rotation.angle) {
+        return result
+    }
+
+    let (new_width, new_height) = compute_rotated_dims(width, height, rotation.angle);
+    buffer_len(new_width, new_height, channels)?;
+
+    Ok((new_width,new_height,sample_rotated(buf, empty, channels, &RotatedCanvas::new((width, height), (new_width, new_height), &rotation, rounding))))
+}
+
+/// Brings an angle (in radians) into the range (-PI, PI], angles already in range are returned untouched
+fn normalize_angle(angle: f64) -> f64 {
+    use std::f64::consts::{PI, TAU};
+
+    if -PI < angle && angle <= PI {
+        return angle
+    }
+
+    let wrapped = angle.rem_euclid(TAU);
+    if wrapped > PI { wrapped - TAU } else { wrapped }
+}
+
+/// An angle brought into range by normalize_angle() along with its sine and cosine,
+/// computed once so that rotating an image and mapping points onto it use the exact same values
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Rotation {
+    angle: f64,
+    sin: f64,
+    cos: f64
+}
+
+impl Rotation {
+    pub(crate) fn new(angle: f64) -> Rotation {
+        let angle = normalize_angle(angle);
+        let (sin, cos) = angle.sin_cos();
+
+        Rotation { angle, sin, cos }
+    }
+}
+
+/// The no. of anticlockwise quarter turns (0 to 3) the angle is (nearly) equal to, None if it is not a whole number of them
+fn quarter_turns(angle: f64) -> Option<i64> {
+    let quarters = angle/std::f64::consts::FRAC_PI_2;
+    let rounded = quarters.round();
+
+    if (quarters-rounded).abs() > 1e-9 {
+        return None
+    }
+
+    Some((rounded as i64).rem_euclid(4))
+}
+
+/// Rotates exactly using rotate_90(), rotate_180() or rotate_270() when the angle is (nearly) one of those,
+/// and returns a copy of the image when it is (nearly) 0. Returns None for every other angle
+///
+/// Going through the sampling instead would waste time and could make the canvas a pixel larger than the image from rounding
+fn rotate_quarter_turns<T: Clone>(buf: &[T], channels: usize, width: usize, height: usize, angle: f64) -> Option<StitchingResult<T>> {
+    match quarter_turns(angle)? {
+        0 => Some(Ok((width,height,buf.to_vec()))),
+        1 => Some(rotate_90(buf, width, height, channels)),
+        2 => Some(rotate_180(buf, width, height, channels)),
+        _ => Some(rotate_270(buf, width, height, channels))
+    }
+}
+
+/// Computes the dimensions of the canvas needed to fit an image of the given dimensions after rotation
+fn compute_rotated_dims(width: usize, height: usize, angle: f64) -> (usize, usize) {
+    let widthf = width as f64;
+    let heightf = height as f64;
+
+    let diag_length = (widthf.powf(2.0)+heightf.powf(2.0)).sqrt();
+    let diag_angle_with_horizontal = (heightf/widthf).atan();
+    let diag_angle_1 = diag_angle_with_horizontal + angle;
+    let diag_angle_2 = -diag_angle_with_horizontal + angle;
+
+    let new_width = (f64::max(diag_angle_1.cos().abs(), diag_angle_2.cos().abs())*diag_length).round();
+    let new_height = (f64::max(diag_angle_1.sin().abs(), diag_angle_2.sin().abs())*diag_length).round();
+
+    (new_width as usize, new_height as usize)
+}
+
+/// The affine transform fast_rotate() applies to an image, so coordinates can be mapped the same way as the pixels, e.g. by a renderer
+///
+/// # Arguments
+///
+/// - width, height - Dimensions of the source image
+/// - angle - The angle of rotation (in radians),
+///   - positive => Anticlockwise,
+///   - negative => Clockwise
+///
+/// Returns the dimensions of the rotated image along with the matrix [a, b, c, d, e, f] mapping the point (x, y) of the source
+/// to (a\*x + b\*y + c, d\*x + e\*y + f) in the rotated image, and its inverse mapping back.
+/// Points are continuous, so pixel (x, y) covers x..x+1 and y..y+1 and the center of the source maps onto the center of the rotated image.
+/// The linear part is the same as the matrix warp_affine() takes
+pub fn rotation_transform(width: usize, height: usize, angle: f64) -> ((usize,usize), [f64; 6], [f64; 6]) {
+    let angle = normalize_angle(angle);
+    let (new_width, new_height) = compute_rotated_dims(width, height, angle);
+
+    let sin = angle.sin();
+    let cos = angle.cos();
+    let center = (width as f64/2.0, height as f64/2.0);
+    let new_center = (new_width as f64/2.0, new_height as f64/2.0);
+
+    // Rotate about the source center, then move it onto the center of the canvas
+    let forward = [
+        cos, sin, new_center.0 - (cos*center.0 + sin*center.1),
+        -sin, cos, new_center.1 - (-sin*center.0 + cos*center.1)
+    ];
+    let inverse = [
+        cos, -sin, center.0 - (cos*new_center.0 - sin*new_center.1),
+        sin, cos, center.1 - (sin*new_center.0 + cos*new_center.1)
+    ];
+
+    ((new_width, new_height), forward, inverse)
+}
+
+/// How the pixels of a rotated canvas map back onto the source image, shared by every row that sample_rotated() fills
+struct RotatedCanvas {
+    /// (Width, Height) of the source image
+    source: (usize,usize),
+    /// (Width, Height) of the canvas
+    canvas: (usize,usize),
+    sin: f64,
+    cos: f64,
+    rounding: RoundingPolicy
+}
+
+impl RotatedCanvas {
+    fn new(source: (usize,usize), canvas: (usize,usize), rotation: &Rotation, rounding: RoundingPolicy) -> RotatedCanvas {
+        RotatedCanvas { source, canvas, sin: rotation.sin, cos: rotation.cos, rounding }
+    }
+}
+
+/// Fills a canvas of the given dimensions by mapping each of its pixels back onto the source image,
+/// the rotation happens about the centers of the source and the canvas
+fn sample_rotated<T: Clone>(buf: &[T], empty: &[T], channels: usize, canvas: &RotatedCanvas) -> Vec<T> {
+    let mut result_buffer: Vec<T> = Vec::with_capacity(canvas.canvas.0*canvas.canvas.1*channels);
+    sample_rotated_into(buf, empty, channels, canvas, &mut result_buffer);
+
+    result_buffer
+}
+
+/// Same as sample_rotated() but the canvas replaces the contents of out, reusing its allocation
+fn sample_rotated_into<T: Clone>(buf: &[T], empty: &[T], channels: usize, canvas: &RotatedCanvas, out: &mut Vec<T>) {
+    let (new_width, new_height) = canvas.canvas;
+
+    let row_length = new_width*channels;
+    out.clear();
+    out.reserve(new_width*new_height*channels);
+    for _ in 0..new_width*new_height {
+        out.extend_from_slice(empty);
+    }
+
+    if row_length == 0 {
+        return
+    }
+
+    for (y, row) in out.chunks_mut(row_length).enumerate() {
+        sample_rotated_row(buf, channels, canvas, y, row);
+    }
+}
+
+/// Fills one row of the canvas for sample_rotated(), pixels that map outside the source are left untouched
+fn sample_rotated_row<T: Clone>(buf: &[T], channels: usize, canvas: &RotatedCanvas, y: usize, row: &mut [T]) {
+    let RotatedCanvas { source: (width, height), sin, cos, rounding, .. } = *canvas;
+    let widthf = width as f64;
+    let heightf = height as f64;
+    let new_width = canvas.canvas.0 as f64;
+    let new_height = canvas.canvas.1 as f64;
+    let y = y as f64;
+
+    for (x, pixel) in row.chunks_mut(channels).enumerate() {
+        let x = x as f64;
+
+        let pos: [f64; 2] = [x+0.5-(new_width)/2.0,y+0.5-(new_height)/2.0];
+
+        let x_along_oldx: f64 = rounding.snap((pos[0]*cos - pos[1]*sin)+(widthf/2.0)-0.5);
+
+        if 0.0 <= x_along_oldx && x_along_oldx < widthf {
+            let y_along_oldy: f64 = rounding.snap((pos[0]*sin + pos[1]*cos)+(heightf/2.0)-0.5);
+
+            if 0.0 <= y_along_oldy && y_along_oldy < heightf {
+                let index = ((y_along_oldy*width as f64)+x_along_oldx) as usize*channels;
+                pixel.clone_from_slice(&buf[index..index+channels]);
+            }
+        }
+    }
+}
+
+/// Same as fast_rotate() but the number of channels is a const generic, which lets the compiler specialise the pixel copies.
+/// Noticeably faster for the common 4 channel (RGBA) case
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - empty - Empty space will be filled with this value, its length is the no. of channels per pixel
+/// - width, height - Dimensions of image
+/// - angle - The angle of rotation (in radians),
+///   - positive => Anticlockwise,
+///   - negative => Clockwise
+pub fn fast_rotate_n<T: Clone + std::fmt::Debug, const C: usize>(buf: &[T], empty: &[T; C], width: usize, height: usize, angle: f64) -> StitchingResult<T> {
+    check_buffer(buf, C, width, height)?;
+    let angle = normalize_angle(angle);
+
+    if let Some(result) = rotate_quarter_turns(buf, C, width, height, angle) {
+        return result
+    }
+
+    let (new_width, new_height) = compute_rotated_dims(width, height, angle);
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(new_width, new_height, C)?);
+
+    let sin = angle.sin();
+    let cos = angle.cos();
+    let widthf = width as f64;
+    let heightf = height as f64;
 
     for y in 0..new_height {
-        // let y_component = y*factor*width;
+        let pos_y = y as f64+0.5-(new_height as f64)/2.0;
 
         for x in 0..new_width {
-            // let index = y_component + x*factor*channels;
+            let pos_x = x as f64+0.5-(new_width as f64)/2.0;
+
+            let x_along_oldx: f64 = ((pos_x*cos - pos_y*sin)+(widthf/2.0)-0.5).round();
+            let y_along_oldy: f64 = ((pos_x*sin + pos_y*cos)+(heightf/2.0)-0.5).round();
 
-            result_buffer.extend_from_slice(&find_mode(buf, channels, width, x*factor, y*factor, factor))
+            if 0.0 <= x_along_oldx && x_along_oldx < widthf && 0.0 <= y_along_oldy && y_along_oldy < heightf {
+                let index = (y_along_oldy as usize*width+x_along_oldx as usize)*C;
+                result_buffer.extend_from_slice(&buf[index..index+C]);
+            } else {
+                result_buffer.extend_from_slice(empty);
+            }
         }
     }
 
-    (new_width,new_height,result_buffer)
+    Ok((new_width,new_height,result_buffer))
+}
+
+/// Same as fast_rotate() but splits the rows of the output across threads using rayon,
+/// worth it for large images
+#[cfg(feature = "parallel")]
+pub fn fast_rotate_parallel<T: Clone + Send + Sync + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64) -> StitchingResult<T> {
+    use rayon::prelude::*;
+
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+    let rotation = Rotation::new(angle);
+
+    if let Some(result) = rotate_quarter_turns(buf, channels, width, height, rotation.angle) {
+        return result
+    }
+
+    let (new_width, new_height) = compute_rotated_dims(width, height, rotation.angle);
+    buffer_len(new_width, new_height, channels)?;
+
+    let canvas = RotatedCanvas::new((width, height), (new_width, new_height), &rotation, RoundingPolicy::Round);
+
+    let row_length = new_width*channels;
+    let mut result_buffer: Vec<T> = filled_canvas(empty, new_width*new_height);
+
+    if row_length > 0 {
+        result_buffer.par_chunks_mut(row_length).enumerate().for_each(|(y, row)| {
+            sample_rotated_row(buf, channels, &canvas, y, row);
+        });
+    }
+
+    Ok((new_width,new_height,result_buffer))
+}
+
+/// Same as fancy_rotate() but takes the angle in degrees
+/// 
+/// - angle - The angle of rotation (in degrees), 
+///   - positive => Anticlockwise, 
+///   - negative => Clockwise
+pub fn fancy_rotate_deg<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64) -> StitchingResult<T> where [T]: Eq + std::hash::Hash{
+    fancy_rotate(buf, empty, channels, width, height, angle.to_radians())
+}
+
+/// Same as fast_rotate() but takes the angle in degrees
+/// 
+/// - angle - The angle of rotation (in degrees), 
+///   - positive => Anticlockwise, 
+///   - negative => Clockwise
+pub fn fast_rotate_deg<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64) -> StitchingResult<T> {
+    fast_rotate(buf, empty, channels, width, height, angle.to_radians())
 }
 
-fn find_mode<T>(buf: &[T], channels: usize, width: usize, startx: usize, starty: usize, block_size: usize) -> &[T] where [T]: Eq + std::hash::Hash + std::fmt::Debug {
+/// Same as fast_rotate() but edge_mode decides what the corners of the canvas outside the source image are filled with
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel, must be at least 1
+/// - angle - The angle of rotation (in radians),
+///   - positive => Anticlockwise,
+///   - negative => Clockwise
+/// - edge_mode - How canvas pixels that map outside the source image are filled
+///
+/// EdgeMode::Wrap and EdgeMode::Reflect are useful for rotating seamless textures and tiled backgrounds
+pub fn fast_rotate_with_edges<T: Clone + std::fmt::Debug>(buf: &[T], channels: usize, width: usize, height: usize, angle: f64, edge_mode: EdgeMode<T>) -> StitchingResult<T> {
+    if let EdgeMode::Fill(empty) = edge_mode {
+        return fast_rotate(buf, empty, channels, width, height, angle)
+    }
+
+    check_buffer(buf, channels, width, height)?;
+    let angle = normalize_angle(angle);
+
+    if let Some(result) = rotate_quarter_turns(buf, channels, width, height, angle) {
+        return result
+    }
+
+    let (new_width, new_height) = compute_rotated_dims(width, height, angle);
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(new_width, new_height, channels)?);
 
-    println!("Called with {}, {}, {}, {}",width,startx,starty,block_size);
+    let sin = angle.sin();
+    let cos = angle.cos();
 
-    let mut count: std::collections::HashMap<&[T], u8> = HashMap::new();
-    let mut max_val: &[T] = &buf[0..channels];
-    let mut max_count: u8 = 0;
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let pos: [f64; 2] = [x as f64+0.5-(new_width as f64)/2.0,y as f64+0.5-(new_height as f64)/2.0];
 
-    for x in startx..startx+block_size {
-        for y in starty..starty+block_size {
+            let x_along_oldx = ((pos[0]*cos - pos[1]*sin)+(width as f64/2.0)-0.5).round() as isize;
+            let y_along_oldy = ((pos[0]*sin + pos[1]*cos)+(height as f64/2.0)-0.5).round() as isize;
 
-            let index = (y*width+x)*channels;
-            *count.entry(&buf[index..index+channels]).or_insert(0) += 1;
+            let index = (edge_mode.resolve(y_along_oldy, height)*width+edge_mode.resolve(x_along_oldx, width))*channels;
+            result_buffer.extend_from_slice(&buf[index..index+channels]);
         }
     }
 
-    println!("Counted {:?}",count);
+    Ok((new_width,new_height,result_buffer))
+}
+
+/// Same as fast_rotate_with_edges() but specialised for single channel images such as masks and heightmaps,
+/// which skips the per pixel slicing of the general code and is considerably faster
+///
+/// # Arguments
+///
+/// - buf - The image, one value per pixel
+/// - width, height - Dimensions of image
+/// - angle - The angle of rotation (in radians),
+///   - positive => Anticlockwise,
+///   - negative => Clockwise
+/// - edge_mode - How canvas pixels that map outside the source image are filled, EdgeMode::Fill takes a single value
+///
+/// Gives exactly the same result as fast_rotate_with_edges() with 1 channel
+pub fn rotate_gray<T: Copy + std::fmt::Debug>(buf: &[T], width: usize, height: usize, angle: f64, edge_mode: EdgeMode<T>) -> StitchingResult<T> {
+    check_buffer(buf, 1, width, height)?;
+    let fill = match edge_mode {
+        EdgeMode::Fill(empty) => {
+            check_empty(empty, 1)?;
+            Some(empty[0])
+        },
+        _ => None
+    };
+    let angle = normalize_angle(angle);
+
+    if let Some(result) = rotate_quarter_turns(buf, 1, width, height, angle) {
+        return result
+    }
+
+    let (new_width, new_height) = compute_rotated_dims(width, height, angle);
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(new_width, new_height, 1)?);
+
+    let sin = angle.sin();
+    let cos = angle.cos();
+    let (half_width, half_height) = (width as f64/2.0, height as f64/2.0);
+
+    // The x offsets of each column are the same on every row, so their products are only computed once
+    let columns: Vec<(f64,f64)> = (0..new_width).map(|x| {
+        let pos = x as f64+0.5-(new_width as f64)/2.0;
+        (pos*cos, pos*sin)
+    }).collect();
 
-    for (key,value) in count.iter() {
-        if *value > max_count {
-            max_count = *value;
-            max_val = key;
+    for y in 0..new_height {
+        let pos = y as f64+0.5-(new_height as f64)/2.0;
+        let (row_sin, row_cos) = (pos*sin, pos*cos);
+
+        for (column_cos, column_sin) in columns.iter() {
+            let x_along_oldx = ((column_cos - row_sin)+half_width-0.5).round() as isize;
+            let y_along_oldy = ((column_sin + row_cos)+half_height-0.5).round() as isize;
+
+            let value = match fill {
+                Some(empty) if x_along_oldx < 0 || y_along_oldy < 0 || x_along_oldx >= width as isize || y_along_oldy >= height as isize => empty,
+                _ => buf[edge_mode.resolve(y_along_oldy, height)*width+edge_mode.resolve(x_along_oldx, width)]
+            };
+            result_buffer.push(value);
         }
     }
 
-    println!("Returning {:?}",max_val);
+    Ok((new_width,new_height,result_buffer))
+}
+
+/// Rotates an image using the fast algorithm while keeping the original dimensions,
+/// parts of the image that rotate out of frame are clipped
+/// 
+/// # Arguments
+/// 
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - empty - Revealed corners will be filled with this value
+/// - angle - The angle of rotation (in radians), 
+///   - positive => Anticlockwise, 
+///   - negative => Clockwise
+/// 
+/// The result is a view of the centre of what fast_rotate() would return, cropped to width x height
+pub fn rotate_fixed<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+
+    let result_buffer = sample_rotated(buf, empty, channels, &RotatedCanvas::new((width, height), (width, height), &Rotation::new(angle), RoundingPolicy::Round));
 
-    return max_val;
+    Ok((width,height,result_buffer))
 }
 
-fn upscale<T>(buf: &[T],channels: usize,width: usize,height: usize) -> (usize,usize,Vec<T>)
-where T: Clone + std::fmt::Debug, [T]: Eq
-{
-    let new_width: usize = width*2 as usize;
-    let new_height: usize = height*2 as usize;
-
-    let row_offset = width*channels;
-
-    let mut scaled = vec![buf[0].clone(); new_width * new_height * channels];
-
-    // Apply the algorithm to the center
-    for y in 1..height as usize - 1 {
-        let source_y_offset = y * row_offset;
-        let scaled_y_offset = y * 2 * new_width * channels;
-
-        let up_offset = source_y_offset - row_offset;
-        let down_offset = source_y_offset + row_offset;
-
-        for x in 1..width as usize - 1 {
-            let pos = source_y_offset + x * channels;
-            apply_scale2x_block(
-                &mut scaled,
-                scaled_y_offset + x * 2 * channels,
-                channels,
-                new_width,
-                (
-                    // Center
-                    &buf[pos..pos+channels],
-                    // Up
-                    &buf[pos-row_offset..pos-row_offset+channels],
-                    // Left
-                    &buf[pos-channels..pos],
-                    // Down
-                    &buf[pos+row_offset..pos+row_offset+channels],
-                    // Right
-                    &buf[pos+channels..pos+2*channels],
-                ),
-            );
-        }
-
-        // Left most column
-        let p = &buf[source_y_offset..source_y_offset+channels];
-        apply_scale2x_block(
-            &mut scaled,
-            scaled_y_offset,
-            channels,
-            new_width,
-            (p, &buf[up_offset..up_offset+channels], p, &buf[down_offset..down_offset+channels], &buf[source_y_offset+channels..source_y_offset+2*channels]),
-        );
+/// Rotates an image and also returns a single channel coverage mask of the result,
+/// useful for effects like outlines and shadows or for collision
+/// 
+/// # Arguments
+/// 
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - empty - Empty space will be filled with this value
+/// - angle - The angle of rotation (in radians), see fast_rotate()
+/// - quality - The rotation algorithm to use
+/// 
+/// The mask has the same dimensions as the rotated image, with 255 wherever the rotated pixel is not equal to empty and 0 elsewhere
+pub fn rotate_with_mask<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, angle: f64, quality: StitchingQuality) -> MaskedResult<T> where [T]: Eq + std::hash::Hash{
+    let rotated = rotate_with_quality(buf, empty, channels, width, height, angle, &quality)?;
 
-        // Right most column
-        let index = source_y_offset+row_offset-channels;
-        let p = &buf[index..index+channels];
-        apply_scale2x_block(
-            &mut scaled,
-            scaled_y_offset + (new_width - 2)*channels,
-            channels,
-            new_width,
-            (p, &buf[source_y_offset-channels..source_y_offset], &buf[index-channels..index], &buf[index+row_offset..index+row_offset+channels], p),
-        );
+    let mask = rotated.2.chunks(channels).map(|pixel| if pixel == empty { 0 } else { 255 }).collect();
+
+    Ok((rotated,mask))
+}
+
+/// Rotates an image about a pivot pixel using the fast algorithm,
+/// the canvas is grown to fit the rotated image just like fast_rotate()
+/// 
+/// # Arguments
+/// 
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - empty - Empty space will be filled with this value
+/// - pivot - The pixel to rotate about, it may lie on or outside the border of the image
+/// - angle - The angle of rotation (in radians), 
+///   - positive => Anticlockwise, 
+///   - negative => Clockwise
+/// 
+/// Returns the rotated image along with the position the pivot landed at in it.
+/// The position is signed as a pivot outside the image can land outside the rotated image too
+pub fn rotate_about<T: Clone + std::fmt::Debug>(buf: &[T], empty: &[T], channels: usize, width: usize, height: usize, pivot: (usize,usize), angle: f64) -> PositionedResult<T, (isize,isize)> {
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+    let angle = normalize_angle(angle);
+
+    let sin = angle.sin();
+    let cos = angle.cos();
+
+    let widthf = width as f64;
+    let heightf = height as f64;
+    let pivotf = (pivot.0 as f64, pivot.1 as f64);
+
+    // Edges of the image relative to the center of the pivot pixel
+    let corners = [
+        (-0.5-pivotf.0, -0.5-pivotf.1),
+        (widthf-0.5-pivotf.0, -0.5-pivotf.1),
+        (-0.5-pivotf.0, heightf-0.5-pivotf.1),
+        (widthf-0.5-pivotf.0, heightf-0.5-pivotf.1)
+    ];
+
+    let mut min = (f64::MAX, f64::MAX);
+    let mut max = (f64::MIN, f64::MIN);
+
+    for (x, y) in corners.iter() {
+        let rotated = (x*cos + y*sin, y*cos - x*sin);
+        min = (f64::min(min.0, rotated.0), f64::min(min.1, rotated.1));
+        max = (f64::max(max.0, rotated.0), f64::max(max.1, rotated.1));
     }
 
-    println!("Now Doing Top and Bottom");
+    // Position of the pivot in the output, and the distance from it to the last pixel
+    let pivot_position = ((-min.0-0.5).round(), (-min.1-0.5).round());
+    let new_width = (pivot_position.0 + (max.0-0.5).round() + 1.0).max(0.0) as usize;
+    let new_height = (pivot_position.1 + (max.1-0.5).round() + 1.0).max(0.0) as usize;
 
-    for x in 1..width - 1 {
-        // Apply the algorithm to the first row
-        let x_offset = x*channels; 
-        let p = &buf[x_offset..x_offset+channels];
-        apply_scale2x_block(
-            &mut scaled,
-            x*channels*2,
-            channels,
-            new_width,
-            (p, p, &buf[x_offset-channels..x_offset], &buf[x_offset+row_offset..x_offset+row_offset+channels], &buf[x_offset+channels..x_offset+2*channels]),
-        );
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(new_width, new_height, channels)?);
 
-        // Apply the algorithm to the last row
-        let index = (height - 1) * width * channels + x_offset;
-        let p = &buf[index..index+channels];
-        let scaled_y_this = ((height - 1) * 2) * new_width * channels;
-        apply_scale2x_block(
-            &mut scaled,
-            scaled_y_this + x*channels*2,
-            channels,
-            new_width,
-            (p, &buf[index-row_offset..index-row_offset+channels], &buf[index-channels..index], p, &buf[index+channels..index+2*channels]),
-        );
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let pos = (x as f64 - pivot_position.0, y as f64 - pivot_position.1);
+
+            let x_along_oldx = (pivotf.0 + pos.0*cos - pos.1*sin).round();
+            let y_along_oldy = (pivotf.1 + pos.0*sin + pos.1*cos).round();
+
+            if 0.0 <= x_along_oldx && x_along_oldx < widthf && 0.0 <= y_along_oldy && y_along_oldy < heightf {
+                let index = (y_along_oldy as usize*width + x_along_oldx as usize)*channels;
+                result_buffer.extend_from_slice(&buf[index..index+channels]);
+            } else {
+                result_buffer.extend_from_slice(empty);
+            }
+        }
     }
 
-    // Apply the algorithms to the corners
-    println!("Now Doing Corners");
+    Ok(((new_width,new_height,result_buffer),(pivot_position.0 as isize,pivot_position.1 as isize)))
+}
 
-    // Top left corner
-    let p = &buf[0..channels];
-    apply_scale2x_block(&mut scaled, 0, channels, new_width, (p, p, p, &buf[row_offset..row_offset+channels], &buf[channels..2*channels]));
+/// Stitches Two images together
+/// 
+/// # Arguments
+/// 
+/// - src, src_dimensions, src_anchor , src_angle - The source image, dimensions and point of stitching and desired angle of stitched appendage
+/// - appendage, appendage_dimensions, appendage_anchor , appendage_angle - The appendage image, dimensions and point of stitching and its current angle in image
+/// - empty - Equivalent of empty pixel
+/// - channels - No. of channels per pixel, must be at least 1
+#[deprecated(note = "use stitch_appendage(), which takes the anchors, angles and order of the appendage as a Joint")]
+#[allow(clippy::too_many_arguments)]
+pub fn stitch<T>(src: &[T], appendage: &[T], empty: &[T], channels: usize, src_dimensions: (usize,usize), src_anchor: (usize,usize), src_angle: f64, appendage_dimensions: (usize,usize), appendage_anchor: (usize,usize), appendage_angle: f64, top: StitchingOrder, quality: StitchingQuality) -> StitchingResult<T> 
+where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+{
+    let joint = Joint::new(src_anchor, src_angle, appendage_anchor, appendage_angle, top);
 
-    // Top right corner
-    let x_right = width - 1;
-    let p = &buf[row_offset-channels..row_offset];
-    apply_scale2x_block(
-        &mut scaled,
-        (new_width-2)*channels,
-        channels,
-        new_width,
-        (p, p, &buf[row_offset-2*channels..row_offset-channels], &buf[row_offset*2-channels..row_offset*2], p),
-    );
+    stitch_appendage(src, src_dimensions, &Appendage { buf: appendage, dimensions: appendage_dimensions, joint }, empty, channels, quality)
+}
 
-    // Bottom left corner
-    let y_bottom = (height-1)*width*channels;
-    let p = &buf[y_bottom..y_bottom+channels];
-    apply_scale2x_block(
-        &mut scaled,
-        (new_height - 2)*new_width*channels,
-        channels,
-        new_width,
-        (p, &buf[y_bottom-row_offset..y_bottom-row_offset+channels], p, p, &buf[y_bottom+channels..y_bottom+2*channels]),
-    );
+/// Stitches Two images together
+/// 
+/// # Arguments
+/// 
+/// - src, src_dimensions - The source image and its dimensions
+/// - appendage - The appendage image, its dimensions and where it is attached (see Joint)
+/// - empty - Equivalent of empty pixel
+/// - channels - No. of channels per pixel, must be at least 1
+/// - quality - The rotation algorithm used for the appendage
+pub fn stitch_appendage<T>(src: &[T], src_dimensions: (usize,usize), appendage: &Appendage<T>, empty: &[T], channels: usize, quality: StitchingQuality) -> StitchingResult<T> 
+where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+{
+    let (stitched, _) = stitch_with_anchor(src, src_dimensions, appendage, empty, channels, quality)?;
 
-    // Bottom right corner
-    let y_bottom_right = y_bottom + x_right;
-    let p = &buf[y_bottom_right..y_bottom_right+channels];
-    apply_scale2x_block(
-        &mut scaled,
-        ((new_height-2)*new_width+new_width-2)*channels,
-        channels,
-        new_width,
-        (p, &buf[y_bottom_right-row_offset..y_bottom_right-row_offset+channels], &buf[y_bottom_right-channels..y_bottom_right], p, p)
-    );
+    Ok(stitched)
+}
 
-    (new_width,new_height,scaled)
+/// Same as stitch_appendage() but the source and appendage may have different channel counts,
+/// e.g. an RGBA appendage can be stitched onto an RGB source
+///
+/// # Arguments
+///
+/// - src_channels, appendage_channels - No. of channels per pixel of each image,
+///   one of them may have one channel less than the other, in which case it is given an opaque alpha channel (see add_opaque_alpha())
+/// - empty - Equivalent of empty pixel, with as many channels as the richer image
+///
+/// The result has as many channels as the richer image.
+/// Returns ParsingError::ChannelMismatch if the channel counts differ by more than one
+pub fn stitch_mixed_channels<T>(src: &[T], src_channels: usize, src_dimensions: (usize,usize), appendage: &Appendage<T>, appendage_channels: usize, empty: &[T], quality: StitchingQuality) -> StitchingResult<T>
+where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+{
+    if src_channels.abs_diff(appendage_channels) > 1 {
+        return Err(ParsingError::ChannelMismatch)
+    }
+
+    let channels = usize::max(src_channels, appendage_channels);
+    let promote = |buf: &[T], buf_channels: usize, dimensions: (usize,usize)| -> Result<Vec<T>,ParsingError> {
+        if buf_channels < channels {
+            Ok(add_opaque_alpha(buf, dimensions.0, dimensions.1, buf_channels)?.2)
+        } else {
+            Ok(buf.to_vec())
+        }
+    };
+
+    let src = promote(src, src_channels, src_dimensions)?;
+    let appendage_buf = promote(appendage.buf, appendage_channels, appendage.dimensions)?;
+
+    stitch_appendage(&src, src_dimensions, &Appendage { buf: &appendage_buf, ..*appendage }, empty, channels, quality)
+}
+
+/// Same as stitch_appendage() but the alpha of the appendage may come from a separate mask instead of its last channel,
+/// for pipelines that keep color and alpha apart
+///
+/// # Arguments
+///
+/// - appendage - The appendage image, with channels-1 channels (its color only) if there is a mask and channels otherwise
+/// - mask - The alpha value of each pixel of the appendage, with one channel and the same dimensions as the appendage.
+///   None stitches the appendage as is, exactly like stitch_appendage()
+/// - channels - No. of channels per pixel of the source and the result, the last one is alpha
+///
+/// The mask is appended to the appendage as its alpha channel (see add_alpha_mask()), so it is rotated along with it.
+/// Returns ParsingError::BufferSizeMismatch if the mask does not match the dimensions of the appendage
+pub fn stitch_masked<T>(src: &[T], src_dimensions: (usize,usize), appendage: &Appendage<T>, mask: Option<&[T]>, empty: &[T], channels: usize, quality: StitchingQuality) -> StitchingResult<T>
+where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+{
+    let mask = match mask {
+        Some(mask) => mask,
+        None => return stitch_appendage(src, src_dimensions, appendage, empty, channels, quality)
+    };
+
+    // With fewer than 2 channels there is no color left for the appendage, which add_alpha_mask() reports as ParsingError::ZeroChannels
+    let appendage_buf = add_alpha_mask(appendage.buf, appendage.dimensions.0, appendage.dimensions.1, channels.saturating_sub(1), mask)?.2;
+
+    stitch_appendage(src, src_dimensions, &Appendage { buf: &appendage_buf, ..*appendage }, empty, channels, quality)
+}
+
+/// Same as stitch_appendage() but also returns where the anchor ended up in the stitched image,
+/// which is where the next appendage of a rig can be attached
+pub fn stitch_with_anchor<T>(src: &[T], src_dimensions: (usize,usize), appendage: &Appendage<T>, empty: &[T], channels: usize, quality: StitchingQuality) -> PositionedResult<T>
+where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+{
+    stitch_with_config(src, src_dimensions, appendage, empty, channels, quality, &StitchConfig::default())
 }
 
+/// Same as stitch_with_anchor() but with additional options, see StitchConfig
+pub fn stitch_with_config<T>(src: &[T], src_dimensions: (usize,usize), appendage: &Appendage<T>, empty: &[T], channels: usize, quality: StitchingQuality, config: &StitchConfig<T>) -> PositionedResult<T>
+where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+{
+    if config.layout == PixelLayout::Planar {
+        let src = to_interleaved(src, src_dimensions.0, src_dimensions.1, channels)?.2;
+        let appendage_buf = to_interleaved(appendage.buf, appendage.dimensions.0, appendage.dimensions.1, channels)?.2;
+        let config = StitchConfig { layout: PixelLayout::Interleaved, ..config.clone() };
+
+        let ((width, height, result_buffer), anchor) = stitch_with_config(&src, src_dimensions, &Appendage { buf: &appendage_buf, ..*appendage }, empty, channels, quality, &config)?;
+        return Ok((to_planar(&result_buffer, width, height, channels)?, anchor))
+    }
+
+    let Appendage { buf: appendage_buf, dimensions: appendage_dimensions, joint } = *appendage;
+
+    check_buffer(src, channels, src_dimensions.0, src_dimensions.1)?;
+    check_empty(empty, channels)?;
+    check_buffer(appendage_buf, channels, appendage_dimensions.0, appendage_dimensions.1)?;
+    let src_anchor = joint.src_anchor.to_pixel(src_dimensions)?;
+    let appendage_anchor = joint.appendage_anchor.to_pixel(appendage_dimensions)?;
+    let alpha_index = check_config(config, channels)?;
+
+    // The same sine and cosine rotate the appendage and map its anchor
+    let rotation = Rotation::new(joint.src_angle - joint.appendage_angle);
 
-fn apply_scale2x_block<P>(scaled: &mut Vec<P>, pos: usize, channels: usize, width: usize, pixels: (&[P], &[P], &[P], &[P], &[P]))
-where P: Clone + std::fmt::Debug, [P]: Eq
-{   
-    copy_to_vec(scaled, pos, if pixels.2 == pixels.1 && pixels.2 != pixels.3 && pixels.1 != pixels.4 { pixels.1 } else { pixels.0 });
-    copy_to_vec(scaled, pos+channels, if pixels.1 == pixels.4 && pixels.1 != pixels.2 && pixels.4 != pixels.3 { pixels.4 } else { pixels.0 });
-    copy_to_vec(scaled, pos+width*channels, if pixels.3 == pixels.2 && pixels.3 != pixels.4 && pixels.2 != pixels.1 { pixels.2 } else { pixels.0 });
-    copy_to_vec(scaled, pos+(width+1)*channels, if pixels.4 == pixels.3 && pixels.4 != pixels.1 && pixels.3 != pixels.2 { pixels.3 } else { pixels.0 });
-    // scaled[pos..pos+channels] = (if pixels.2 == pixels.1 && pixels.2 != pixels.3 && pixels.1 != pixels.4 { pixels.1 } else { pixels.0 });
-    // scaled[pos+1..pos+1+channels] = (if pixels.1 == pixels.4 && pixels.1 != pixels.2 && pixels.4 != pixels.3 { pixels.4 } else { pixels.0 }).clone();
-    // scaled[pos+width..pos+width+channels] = (if pixels.3 == pixels.2 && pixels.3 != pixels.4 && pixels.2 != pixels.1 { pixels.2 } else { pixels.0 }).clone();
-    // scaled[pos+width+1..pos+width+1+channels] = (if pixels.4 == pixels.3 && pixels.4 != pixels.1 && pixels.3 != pixels.2 { pixels.3 } else { pixels.0 }).clone();
+    let rotated = if config.rotation_mode == RotationMode::Smooth && quality.supersample_factor().is_some() {
+        smooth_rotate_by(appendage_buf, empty, channels, appendage_dimensions.0, appendage_dimensions.1, &rotation)?
+    } else {
+        rotate_keyed(appendage_buf, empty, channels, appendage_dimensions, &rotation, &quality, ModeFilter::packed(config.color_key))?
+    };
+    let anchor = map_rotated_point(appendage_anchor, appendage_dimensions.0, appendage_dimensions.1, (rotated.0, rotated.1), &rotation);
+
+    stitch_rotated(src, src_dimensions, RotatedAppendage { image: rotated, anchor, src_anchor, order: joint.order }, empty, channels, config, alpha_index)
 }
 
-fn copy_to_vec<P: Clone + std::fmt::Debug>(destination: &mut Vec<P>, start_index: usize, source: &[P]) {
-    println!("Called with {:?} to put at index {} on a vec of len {}",source,start_index,destination.len());
-    for (i,item) in source.iter().enumerate() {
-        destination[start_index+i] = item.clone()
+/// Checks the options in config, returning the index of the alpha channel
+pub(crate) fn check_config<T>(config: &StitchConfig<T>, channels: usize) -> Result<Option<usize>,ParsingError> {
+    check_color_key(config.color_key, channels)?;
+
+    if !(0.0..=1.0).contains(&config.opacity) {
+        return Err(ParsingError::OpacityOutOfRange)
     }
-}
\ No newline at end of file
+
+    config.alpha_channel.index(channels)
+}
+
+/// An appendage that has already been rotated, along with where its anchor ended up in the rotated image
+pub(crate) struct RotatedAppendage<T> {
+    pub(crate) image: (usize,usize,Vec<T>),
+    pub(crate) anchor: (usize,usize),
+    pub(crate) src_anchor: (usize,usize),
+    pub(crate) order: StitchingOrder
+}
+
+/// Lays an appendage that has already been rotated onto the source, the part of stitch_with_config() after rotation
+pub(crate) fn stitch_rotated<T>(src: &[T], src_dimensions: (usize,usize), appendage: RotatedAppendage<T>, empty: &[T], channels: usize, config: &StitchConfig<T>, alpha_index: Option<usize>) -> PositionedResult<T>
+where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+{
+    let RotatedAppendage { image: mut rotated, anchor: rotated_anchor_pos, src_anchor, order: top } = appendage;
+
+    if let (Some(alpha_index), true) = (alpha_index, config.opacity < 1.0) {
+        let opacity = config.opacity as f64;
+        for pixel in rotated.2.chunks_mut(channels) {
+            pixel[alpha_index] = T::from_f64(pixel[alpha_index].to_f64()*opacity);
+        }
+    }
+
+    // T, R, B, L
+    // The anchors are checked by the callers and map_rotated_point() clamps onto the rotated image, so these only fail if either is off by one
+    let distances = |anchor: (usize,usize), dimensions: (usize,usize)| -> Result<[usize; 4], ParsingError> {
+        debug_assert!(anchor.0 < dimensions.0 && anchor.1 < dimensions.1, "anchor {:?} lies outside {:?}", anchor, dimensions);
+        Ok([
+            anchor.1,
+            dimensions.0.checked_sub(anchor.0).ok_or(ParsingError::AnchorOutOfBounds)?,
+            dimensions.1.checked_sub(anchor.1).ok_or(ParsingError::AnchorOutOfBounds)?,
+            anchor.0
+        ])
+    };
+
+    let src_dist = distances(src_anchor, src_dimensions)?;
+    let rot_dist = distances(rotated_anchor_pos, (rotated.0, rotated.1))?;
+
+    let dist: Vec<usize> = if config.clip_to_source {
+        src_dist.to_vec()
+    } else {
+        src_dist.iter().zip(rot_dist.iter()).map(|(x, y)| usize::max(*x,*y)).collect()
+    };
+    
+    debug!("Post rotation anchor is at {},{} in an image of {},{}",rotated_anchor_pos.0,rotated_anchor_pos.1,rotated.0,rotated.1);
+
+    let width = dist[1].checked_add(dist[3]).ok_or(ParsingError::DimensionsTooLarge)?;
+    let height = dist[0].checked_add(dist[2]).ok_or(ParsingError::DimensionsTooLarge)?;
+
+    // Can only be negative when clipping to the source
+    let rotated_image_topleft = (dist[3] as isize-rotated_anchor_pos.0 as isize,dist[0] as isize-rotated_anchor_pos.1 as isize);
+    let src_image_topleft = (dist[3]-src_anchor.0,dist[0]-src_anchor.1);
+
+    buffer_len(width, height, channels)?;
+    check_buffer(&rotated.2, channels, rotated.0, rotated.1)?;
+    let mut res = empty.repeat(height*width);
+    debug_assert_eq!(res.len(), width*height*channels);
+
+    debug!("Toplefts are at {},{} and {},{}",rotated_image_topleft.0,rotated_image_topleft.1,src_image_topleft.0,src_image_topleft.1);
+
+    // The canvas always holds the whole source, this only fails if the distances above are off
+    let src_fits = src_image_topleft.0+src_dimensions.0 <= width && src_image_topleft.1+src_dimensions.1 <= height;
+    debug_assert!(src_fits, "source at {:?} does not fit in a canvas of {}x{}", src_image_topleft, width, height);
+    if !src_fits {
+        return Err(ParsingError::AnchorOutOfBounds)
+    }
+
+    let src_row_len = src_dimensions.0*channels;
+    for (y, row) in src.chunks_exact(src_row_len).enumerate() {
+        let start = ((src_image_topleft.1+y)*width+src_image_topleft.0)*channels;
+        res[start..start+src_row_len].copy_from_slice(row);
+    }
+
+    // Every blended pixel is written here first, so blending does not allocate
+    let mut blended: Vec<T> = Vec::with_capacity(channels);
+
+    for y in 0..rotated.1 {
+        for x in 0..rotated.0 {
+            let canvas_x = rotated_image_topleft.0 + x as isize;
+            let canvas_y = rotated_image_topleft.1 + y as isize;
+            if canvas_x < 0 || canvas_y < 0 || canvas_x as usize >= width || canvas_y as usize >= height {
+                continue
+            }
+
+            let appendage_pixel = &rotated.2[y*rotated.0*channels+x*channels..y*rotated.0*channels+x*channels+channels];
+            if is_invisible(appendage_pixel, empty, alpha_index) {
+                continue
+            }
+
+            let canvas_index = (canvas_y as usize*width+canvas_x as usize)*channels;
+            let canvas_pixel = &res[canvas_index..canvas_index+channels];
+
+            // Without an alpha channel the empty fill around the source is the only sign that nothing is there,
+            // so the appendage always shows through it, whichever image is on top
+            if alpha_index.is_none() && canvas_pixel == empty {
+                res[canvas_index..canvas_index+channels].copy_from_slice(appendage_pixel);
+                continue
+            }
+
+            match top {
+                StitchingOrder::AppendageOnTop => blend_with_config(appendage_pixel, canvas_pixel, config, alpha_index, &mut blended),
+                StitchingOrder::SourceOnTop => blend_with_config(canvas_pixel, appendage_pixel, config, alpha_index, &mut blended),
+                StitchingOrder::Blend => match config.color_key {
+                    Some(key) if appendage_pixel == key => copy_pixel(canvas_pixel, &mut blended),
+                    Some(key) if canvas_pixel == key => copy_pixel(appendage_pixel, &mut blended),
+                    _ => blend_symmetric(appendage_pixel, canvas_pixel, alpha_index, &mut blended)
+                }
+            }
+            res[canvas_index..canvas_index+channels].copy_from_slice(&blended);
+        }
+    }
+
+    Ok(((width,height,res),(dist[3],dist[0])))
+}
+
+/// An appendage to be stitched onto a source image by stitch_appendage() and co
+#[derive(Debug)]
+pub struct Appendage<'a, T> {
+    /// The appendage image
+    pub buf: &'a [T],
+    /// Dimensions of the appendage image
+    pub dimensions: (usize,usize),
+    /// Where the appendage is attached to the source
+    pub joint: Joint
+}
+
+// Derived impls would require T: Copy, though only the reference to the image is copied
+impl<T> Clone for Appendage<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Appendage<'_, T> {}
+
+/// Stitches any number of appendages onto one source image
+/// 
+/// # Arguments
+/// 
+/// - src, src_dimensions - The source image and its dimensions
+/// - appendages - The appendages to attach, each with its own anchors, angles and order
+/// - empty - Equivalent of empty pixel
+/// - channels - No. of channels per pixel
+/// - quality - The rotation algorithm used for the appendages
+/// - config - Additional options, see StitchConfig. clip_to_source and layout are not used, the canvas always grows to fit every appendage
+/// 
+/// The appendages are stitched in the order of the vector, as if by one stitch_with_config() after another:
+/// StitchingOrder::AppendageOnTop puts an appendage above the source and every earlier appendage, StitchingOrder::SourceOnTop beneath them.
+/// 
+/// Returns the stitched image along with where the source anchor of each appendage ended up in it, in the same order as appendages
+pub fn stitch_all<T>(src: &[T], empty: &[T], channels: usize, src_dimensions: (usize,usize), appendages: Vec<Appendage<T>>, quality: StitchingQuality, config: &StitchConfig<T>) -> PositionedResult<T, Vec<(usize,usize)>>
+where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+{
+    check_buffer(src, channels, src_dimensions.0, src_dimensions.1)?;
+    check_empty(empty, channels)?;
+    let alpha_index = check_config(config, channels)?;
+
+    let mut layers = Vec::with_capacity(appendages.len());
+
+    // Bounds of the canvas relative to the source's top left
+    let mut min = (0isize, 0isize);
+    let mut max = (src_dimensions.0 as isize, src_dimensions.1 as isize);
+
+    for appendage in appendages.iter() {
+        check_buffer(appendage.buf, channels, appendage.dimensions.0, appendage.dimensions.1)?;
+        let joint = appendage.joint;
+        let src_anchor = joint.src_anchor.to_pixel(src_dimensions)?;
+        let anchor = joint.appendage_anchor.to_pixel(appendage.dimensions)?;
+
+        let rotation = Rotation::new(joint.src_angle - joint.appendage_angle);
+
+        let mut rotated = if config.rotation_mode == RotationMode::Smooth && quality.supersample_factor().is_some() {
+            smooth_rotate_by(appendage.buf, empty, channels, appendage.dimensions.0, appendage.dimensions.1, &rotation)?
+        } else {
+            rotate_keyed(appendage.buf, empty, channels, appendage.dimensions, &rotation, &quality, ModeFilter::packed(config.color_key))?
+        };
+        let rotated_anchor_pos = map_rotated_point(anchor, appendage.dimensions.0, appendage.dimensions.1, (rotated.0, rotated.1), &rotation);
+
+        if let (Some(alpha_index), true) = (alpha_index, config.opacity < 1.0) {
+            let opacity = config.opacity as f64;
+            for pixel in rotated.2.chunks_mut(channels) {
+                pixel[alpha_index] = T::from_f64(pixel[alpha_index].to_f64()*opacity);
+            }
+        }
+
+        let topleft = (
+            src_anchor.0 as isize - rotated_anchor_pos.0 as isize,
+            src_anchor.1 as isize - rotated_anchor_pos.1 as isize
+        );
+
+        min = (isize::min(min.0, topleft.0), isize::min(min.1, topleft.1));
+        max = (isize::max(max.0, topleft.0 + rotated.0 as isize), isize::max(max.1, topleft.1 + rotated.1 as isize));
+
+        layers.push(RotatedAppendage { image: rotated, anchor: rotated_anchor_pos, src_anchor, order: joint.order });
+    }
+
+    let width = (max.0 - min.0) as usize;
+    let height = (max.1 - min.1) as usize;
+    let src_position = ((-min.0) as usize, (-min.1) as usize);
+
+    buffer_len(width, height, channels)?;
+    let mut res = empty.repeat(height*width);
+
+    for y in 0..src_dimensions.1 {
+        let src_row = y*src_dimensions.0*channels;
+        let res_row = ((src_position.1+y)*width+src_position.0)*channels;
+        res[res_row..res_row+src_dimensions.0*channels].clone_from_slice(&src[src_row..src_row+src_dimensions.0*channels]);
+    }
+
+    for layer in layers.iter_mut() {
+        layer.src_anchor = (layer.src_anchor.0+src_position.0, layer.src_anchor.1+src_position.1);
+        composite_layer(&mut res, width, layer, empty, channels, config, alpha_index);
+    }
+
+    Ok(((width,height,res),layers.into_iter().map(|layer| layer.src_anchor).collect()))
+}
+
+/// Composites an image onto an existing destination buffer in place, without allocating a new canvas
+/// 
+/// # Arguments
+/// 
+/// - dest, dest_dimensions - The destination image and its dimensions, with as many channels per pixel as src
+/// - src - The image to composite
+/// - offset - Position of the top left pixel of src in dest, can be negative
+/// - blend_mode - How src is combined with dest
+/// - alpha_channel - Which channel holds the alpha value, with AlphaChannel::Opaque src is blended as fully opaque
+/// 
+/// Parts of src that fall outside dest are clipped,
+/// returns ParsingError::NoOverlap if none of src would land inside dest
+pub fn composite_onto<T>(dest: &mut [T], dest_dimensions: (usize,usize), src: ImageView<T>, offset: (isize,isize), blend_mode: BlendMode, alpha_channel: AlphaChannel) -> Result<(), ParsingError>
+where T: StitchableType
+{
+    let channels = src.channels();
+    check_buffer(dest, channels, dest_dimensions.0, dest_dimensions.1)?;
+    let alpha_index = alpha_channel.index(channels)?;
+
+    // The part of src that lands inside dest, in src coordinates
+    let (start_x, end_x) = clip_span(dest_dimensions.0, src.width(), offset.0);
+    let (start_y, end_y) = clip_span(dest_dimensions.1, src.height(), offset.1);
+
+    if start_x >= end_x || start_y >= end_y {
+        return Err(ParsingError::NoOverlap)
+    }
+
+    let mut blended: Vec<T> = Vec::with_capacity(channels);
+
+    for y in start_y..end_y {
+        for x in start_x..end_x {
+            // Both lie inside dest here, so neither sum can overflow
+            let dest_x = (offset.0 + x as isize) as usize;
+            let dest_y = (offset.1 + y as isize) as usize;
+            let src_index = (y*src.width()+x)*channels;
+            let dest_index = (dest_y*dest_dimensions.0+dest_x)*channels;
+
+            blend_into(&src.data()[src_index..src_index+channels], &dest[dest_index..dest_index+channels], blend_mode, alpha_index, false, &mut blended);
+            dest[dest_index..dest_index+channels].copy_from_slice(&blended);
+        }
+    }
+
+    Ok(())
+}
+
+/// The range of src coordinates along one axis that lands inside dest when src is placed at offset,
+/// computed without negating or subtracting offset so that no offset can overflow
+fn clip_span(dest_length: usize, src_length: usize, offset: isize) -> (usize, usize) {
+    let distance = offset.unsigned_abs();
+
+    if offset < 0 {
+        (distance, usize::min(src_length, dest_length.saturating_add(distance)))
+    } else {
+        (0, usize::min(src_length, dest_length.saturating_sub(distance)))
+    }
+}
+
+/// Adds layers of light together, e.g. for glows where many sprites overlap
+///
+/// # Arguments
+///
+/// - layers - The images to add up, all with the same dimensions and no. of channels
+/// - alpha_channel - Which channel holds the alpha value, colors are weighted by it before they are added
+///   and the alphas are added up too. With AlphaChannel::Opaque every channel is added as is
+///
+/// Unlike BlendMode::Additive, which clamps after every pair of layers, the sums are kept in an f64 per channel
+/// and only clamped to the maximum value of the subpixel type once at the end, so they never wrap around.
+/// Returns ParsingError::EmptyBufferError if there are no layers,
+/// ParsingError::ChannelMismatch or ParsingError::DimensionMismatch if a layer differs from the first one
+pub fn composite_additive<T>(layers: &[Image<T>], alpha_channel: AlphaChannel) -> Result<Image<T>, ParsingError>
+where T: StitchableType
+{
+    let first = layers.first().ok_or(ParsingError::EmptyBufferError)?;
+    let channels = first.channels();
+    let alpha_index = alpha_channel.index(channels)?;
+
+    let max = T::maxvalue().to_f64();
+    let mut sums = vec![0.0; first.data().len()];
+
+    for layer in layers {
+        if layer.channels() != channels {
+            return Err(ParsingError::ChannelMismatch)
+        }
+        if layer.dimensions() != first.dimensions() {
+            return Err(ParsingError::DimensionMismatch { expected: first.dimensions(), actual: layer.dimensions() })
+        }
+
+        for (sum, pixel) in sums.chunks_mut(channels).zip(layer.data().chunks(channels)) {
+            let alpha = alpha_index.map_or(1.0, |index| pixel[index].to_f64()/max);
+
+            for (c, (sum, value)) in sum.iter_mut().zip(pixel.iter()).enumerate() {
+                *sum += if Some(c) == alpha_index { value.to_f64() } else { value.to_f64()*alpha };
+            }
+        }
+    }
+
+    let result_buffer: Vec<T> = sums.into_iter().map(|sum| T::from_f64(f64::min(sum, max))).collect();
+    Image::new(result_buffer, first.width(), first.height(), channels)
+}
+
+/// Blends a rotated appendage into the canvas so its anchor lies on its src_anchor, given in canvas coordinates,
+/// above, below or evenly with what is already there, the same way stitch_rotated() blends
+fn composite_layer<T>(canvas: &mut [T], canvas_width: usize, appendage: &RotatedAppendage<T>, empty: &[T], channels: usize, config: &StitchConfig<T>, alpha_index: Option<usize>)
+where T: StitchableType, [T]: Eq
+{
+    let RotatedAppendage { image: layer, anchor, src_anchor, order } = appendage;
+    // The canvas was grown to fit every layer, so this cannot underflow
+    let position = (src_anchor.0 - anchor.0, src_anchor.1 - anchor.1);
+    let mut blended: Vec<T> = Vec::with_capacity(channels);
+
+    for y in 0..layer.1 {
+        for x in 0..layer.0 {
+            let layer_index = (y*layer.0+x)*channels;
+            let canvas_index = ((position.1+y)*canvas_width+position.0+x)*channels;
+
+            let layer_pixel = &layer.2[layer_index..layer_index+channels];
+            if is_invisible(layer_pixel, empty, alpha_index) {
+                continue
+            }
+
+            let canvas_pixel = &canvas[canvas_index..canvas_index+channels];
+
+            // Without an alpha channel the empty fill is the only sign that nothing is there, see stitch_rotated()
+            if alpha_index.is_none() && canvas_pixel == empty {
+                canvas[canvas_index..canvas_index+channels].copy_from_slice(layer_pixel);
+                continue
+            }
+
+            match order {
+                StitchingOrder::AppendageOnTop => blend_with_config(layer_pixel, canvas_pixel, config, alpha_index, &mut blended),
+                StitchingOrder::SourceOnTop => blend_with_config(canvas_pixel, layer_pixel, config, alpha_index, &mut blended),
+                StitchingOrder::Blend => match config.color_key {
+                    Some(key) if layer_pixel == key => copy_pixel(canvas_pixel, &mut blended),
+                    Some(key) if canvas_pixel == key => copy_pixel(layer_pixel, &mut blended),
+                    _ => blend_symmetric(layer_pixel, canvas_pixel, alpha_index, &mut blended)
+                }
+            }
+            canvas[canvas_index..canvas_index+channels].copy_from_slice(&blended);
+        }
+    }
+}
+
+/// Finds where a point ends up after rotating an image into a canvas of new_dimensions
+/// 
+/// # Arguments
+/// 
+/// - point - The pixel in the source image
+/// - width, height - Dimensions of the source image
+/// - new_dimensions - Dimensions of the rotated image, should be those of the actual rotated image so the two can't drift apart
+/// - angle - The angle of rotation (in radians), 
+///   - positive => Anticlockwise, 
+///   - negative => Clockwise
+///
+/// The result always lies within new_dimensions, points that round to just outside the rotated image are clamped onto its edge
+pub fn rotate_point(point: (usize,usize), width: usize, height: usize, new_dimensions: (usize,usize), angle: f64) -> (usize,usize) {
+    debug!("Rotate Point:  dims {}x{}",new_dimensions.0,new_dimensions.1);
+
+    map_rotated_point(point, width, height, new_dimensions, &Rotation::new(angle))
+}
+
+/// Same as rotate_point() for many points at once, e.g. the vertices of a collision polygon or several anchors on one sprite.
+/// The dimensions of the rotated image and the trigonometry are only computed once for all of them
+///
+/// # Arguments
+///
+/// - points - The pixels in the source image
+/// - width, height - Dimensions of the source image
+/// - angle - The angle of rotation (in radians),
+///   - positive => Anticlockwise,
+///   - negative => Clockwise
+///
+/// The points are mapped into an image with the dimensions fast_rotate() gives the rotated image.
+/// Returns the rotated points in the same order
+pub fn rotate_points(points: &[(usize,usize)], width: usize, height: usize, angle: f64) -> Vec<(usize,usize)> {
+    let rotation = Rotation::new(angle);
+    let new_dimensions = compute_rotated_dims(width, height, rotation.angle);
+
+    points.iter().map(|point| map_rotated_point(*point, width, height, new_dimensions, &rotation)).collect()
+}
+
+/// Rotates a single point for rotate_point(), rotate_points() and the stitching functions
+fn map_rotated_point(point: (usize,usize), width: usize, height: usize, new_dimensions: (usize,usize), rotation: &Rotation) -> (usize,usize) {
+    let (sin, cos) = (rotation.sin, rotation.cos);
+    let widthf = width as f64;
+    let heightf = height as f64;
+    let new_width = new_dimensions.0 as f64;
+    let new_height = new_dimensions.1 as f64;
+
+    let pos_vector = (
+        point.0 as f64 - widthf/2.0 + 0.5,
+        point.1 as f64 - heightf/2.0 + 0.5,
+    );
+
+    let point_rotated_wrt_center = (
+        pos_vector.0*cos + pos_vector.1*sin,
+        pos_vector.1*cos - pos_vector.0*sin
+    );
+
+    // Rounding can push points on the edge just outside the canvas, so they are clamped back onto it instead of wrapping or overflowing
+    let clamp = |value: f64, length: usize| usize::min(value.max(0.0) as usize, length.saturating_sub(1));
+
+    let final_point = (
+        clamp(point_rotated_wrt_center.0 + new_width/2.0, new_dimensions.0),
+        clamp(point_rotated_wrt_center.1 + new_height/2.0, new_dimensions.1)
+    );
+
+    debug!("Rotate Point: vec {:?} rotated to {:?} resolved as {:?}",pos_vector,point_rotated_wrt_center,final_point);
+
+    final_point
+}
+
+/// Maps a pixel of a rotated image back to the position in the source image it was sampled from,
+/// the inverse of rotate_point()
+/// 
+/// # Arguments
+/// 
+/// - point - The pixel in the rotated image
+/// - width, height - Dimensions of the source image
+/// - new_dimensions - Dimensions of the rotated image
+/// - angle - The angle the image was rotated by (in radians)
+/// 
+/// Returns None if the pixel maps to a point outside the source image, i.e. it was filled with the empty pixel
+pub fn inverse_rotate_point(point: (usize,usize), width: usize, height: usize, new_dimensions: (usize,usize), angle: f64) -> Option<(f64,f64)> {
+    let angle = normalize_angle(angle);
+    let sin = angle.sin();
+    let cos = angle.cos();
+
+    let widthf = width as f64;
+    let heightf = height as f64;
+
+    let pos_vector = (
+        point.0 as f64 + 0.5 - new_dimensions.0 as f64/2.0,
+        point.1 as f64 + 0.5 - new_dimensions.1 as f64/2.0
+    );
+
+    let source_point = (
+        pos_vector.0*cos - pos_vector.1*sin + widthf/2.0 - 0.5,
+        pos_vector.0*sin + pos_vector.1*cos + heightf/2.0 - 0.5
+    );
+
+    let rounded = (source_point.0.round(), source_point.1.round());
+
+    if rounded.0 < 0.0 || rounded.0 >= widthf || rounded.1 < 0.0 || rounded.1 >= heightf {
+        return None
+    }
+
+    Some(source_point)
+}
+
+/// Whether an appendage pixel would leave whatever it is blended with unchanged,
+/// i.e. it is the empty fill or fully transparent, so blending it can be skipped
+fn is_invisible<T>(pixel: &[T], empty: &[T], alpha_index: Option<usize>) -> bool
+where T: StitchableType, [T]: Eq
+{
+    pixel == empty || alpha_index.map_or(false, |index| pixel[index].to_f64() == 0.0)
+}
+
+/// Blends two pixels with the options in config into out, pixels equal to the color key are treated as transparent
+fn blend_with_config<T>(top: &[T], bottom: &[T], config: &StitchConfig<T>, alpha_index: Option<usize>, out: &mut Vec<T>)
+where T: StitchableType, [T]: Eq
+{
+    match config.color_key {
+        Some(key) if top == key => copy_pixel(bottom, out),
+        Some(key) if bottom == key => copy_pixel(top, out),
+        _ => blend_into(top, bottom, config.blend_mode, alpha_index, config.gamma_correct, out)
+    }
+}
+
+/// Replaces the contents of out with pixel
+fn copy_pixel<T: Clone>(pixel: &[T], out: &mut Vec<T>) {
+    out.clear();
+    out.extend_from_slice(pixel);
+}
+
+/// Composites a single pixel over another, the same way stitch_appendage() does, for custom compositing pipelines
+///
+/// # Arguments
+///
+/// - top, bottom - The pixels, with the same no. of channels
+/// - mode - How the top pixel is combined with the bottom one
+/// - alpha_index - The channel holding the alpha value of both pixels, if None the top pixel replaces the bottom one
+///
+/// Colors are mixed with the blend mode where the pixels overlap and then laid over the bottom pixel with straight alpha "source over".
+/// Panics if bottom is shorter than top or alpha_index is not a channel of both pixels
+pub fn blend_pixel<T>(top: &[T], bottom: &[T], mode: BlendMode, alpha_index: Option<usize>) -> Vec<T>
+where T: StitchableType
+{
+    blend(top, bottom, mode, alpha_index, false)
+}
+
+/// Composites the top pixel over the bottom one using the given blend mode,
+/// alpha_index is the channel holding the alpha value of each pixel, if there is none the top pixel replaces the bottom one
+/// 
+/// Colors are mixed with the blend mode where the pixels overlap and then laid over the bottom pixel with straight alpha "source over",
+/// if gamma_correct is set this is done in linear light
+fn blend<T>(top: &[T], bottom: &[T], mode: BlendMode, alpha_index: Option<usize>, gamma_correct: bool) -> Vec<T>
+where T: StitchableType
+{
+    let mut res: Vec<T> = Vec::with_capacity(top.len());
+    blend_into(top, bottom, mode, alpha_index, gamma_correct, &mut res);
+
+    res
+}
+
+/// Same as blend() but the result replaces the contents of out, so one buffer can be reused for every pixel
+fn blend_into<T>(top: &[T], bottom: &[T], mode: BlendMode, alpha_index: Option<usize>, gamma_correct: bool, out: &mut Vec<T>)
+where T: StitchableType
+{
+    out.clear();
+
+    let alpha_index = match alpha_index {
+        Some(index) => index,
+        None => {
+            out.extend_from_slice(top);
+            return
+        }
+    };
+
+    let max = T::maxvalue().to_f64();
+
+    let top_alpha = top[alpha_index].to_f64()/max;
+    let bottom_alpha = bottom[alpha_index].to_f64()/max;
+
+    if let BlendMode::Replace = mode {
+        out.extend_from_slice(if top_alpha > 0.0 { top } else { bottom });
+        return
+    }
+
+    // Skip the float round trip where the result is one of the pixels, as wide types like u64 have more bits than an f64 can hold
+    if top_alpha <= 0.0 {
+        out.extend_from_slice(bottom);
+        return
+    }
+    if top_alpha >= 1.0 && mode == BlendMode::SourceOver {
+        out.extend_from_slice(top);
+        return
+    }
+
+    let bottom_weight = bottom_alpha*(1.0-top_alpha);
+    let composite_alpha = top_alpha + bottom_weight;
+
+    // Nothing is visible, keep the bottom pixel as is
+    if composite_alpha <= 0.0 {
+        out.extend_from_slice(bottom);
+        return
+    }
+
+    for i in 0..top.len() {
+        if i == alpha_index {
+            out.push(T::from_f64(composite_alpha*max));
+            continue
+        }
+
+        let mut top_color = top[i].to_f64()/max;
+        let mut bottom_color = bottom[i].to_f64()/max;
+
+        if gamma_correct {
+            top_color = srgb_to_linear(top_color);
+            bottom_color = srgb_to_linear(bottom_color);
+        }
+
+        let mixed = match mode {
+            BlendMode::Multiply => top_color*bottom_color,
+            BlendMode::Screen => top_color + bottom_color - top_color*bottom_color,
+            BlendMode::Additive => f64::min(top_color + bottom_color, 1.0),
+            BlendMode::SourceOver | BlendMode::Replace => top_color
+        };
+
+        // Where there is nothing beneath the top pixel its own color is used as is
+        let top_color = (1.0-bottom_alpha)*top_color + bottom_alpha*mixed;
+
+        let mut color = (top_color*top_alpha + bottom_color*bottom_weight)/composite_alpha;
+
+        if gamma_correct {
+            color = linear_to_srgb(color);
+        }
+
+        out.push(T::from_f64(color*max))
+    }
+}
+
+/// Composites two pixels into out without either being on top, see StitchingOrder::Blend.
+/// The colors are averaged weighted by alpha, and pixels without an alpha channel count as opaque
+fn blend_symmetric<T>(first: &[T], second: &[T], alpha_index: Option<usize>, out: &mut Vec<T>)
+where T: StitchableType
+{
+    let max = T::maxvalue().to_f64();
+    let alpha_of = |pixel: &[T]| alpha_index.map_or(1.0, |index| pixel[index].to_f64()/max);
+
+    let first_alpha = alpha_of(first);
+    let second_alpha = alpha_of(second);
+    let total_weight = first_alpha + second_alpha;
+
+    if total_weight <= 0.0 {
+        copy_pixel(second, out);
+        return
+    }
+
+    out.clear();
+
+    for i in 0..first.len() {
+        if Some(i) == alpha_index {
+            out.push(T::from_f64((first_alpha + second_alpha - first_alpha*second_alpha)*max));
+        } else {
+            out.push(T::from_f64((first[i].to_f64()*first_alpha + second[i].to_f64()*second_alpha)/total_weight));
+        }
+    }
+}
+
+/// Converts a normalised sRGB encoded value to linear light
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value/12.92
+    } else {
+        ((value+0.055)/1.055).powf(2.4)
+    }
+}
+
+/// Converts a normalised linear light value back to sRGB encoding
+fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value*12.92
+    } else {
+        1.055*value.powf(1.0/2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn degrees_match_radians() {
+        let buf: Vec<u8> = (0..5*3*2).map(|value| value as u8).collect();
+
+        assert_eq!(fast_rotate_deg(&buf, &[0,0], 2, 5, 3, 90.0), fast_rotate(&buf, &[0,0], 2, 5, 3, std::f64::consts::PI/2.0));
+    }
+
+    #[test]
+    fn fixed_rotation_keeps_the_dimensions() {
+        let buf: Vec<u8> = (0..7*4*3).map(|value| value as u8).collect();
+        let (width, height, rotated) = rotate_fixed(&buf, &[0,0,0], 3, 7, 4, 30f64.to_radians()).unwrap();
+
+        assert_eq!((width, height, rotated.len()), (7, 4, 7*4*3));
+    }
+
+    #[test]
+    fn half_transparent_red_over_opaque_blue() {
+        assert_eq!(stitch_pixel(&[0u8,0,255,255], &[255,0,0,128], StitchingOrder::AppendageOnTop, &StitchConfig::default()), Ok(vec![128,0,127,255]));
+    }
+
+    #[test]
+    fn stitched_buffer_matches_the_dimensions() {
+        let joint = Joint::new((1,1), 0.5, (0,0), 0.0, StitchingOrder::AppendageOnTop);
+        let appendage = [255u8,0,0,128].repeat(2*3);
+        let (width, height, stitched) = stitch_appendage(&[0u8,0,255,255].repeat(3*4), (3,4), &Appendage { buf: &appendage, dimensions: (2,3), joint }, &[0,0,0,0], 4, StitchingQuality::Fast).unwrap();
+
+        assert_eq!(stitched.len(), width*height*4);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn positional_stitch_matches_stitch_appendage() {
+        let src = [0u8,0,255,255].repeat(3*4);
+        let appendage = [255u8,0,0,128].repeat(2*3);
+        let joint = Joint::new((1,1), 0.5, (0,1), 0.2, StitchingOrder::SourceOnTop);
+
+        assert_eq!(
+            stitch(&src, &appendage, &[0,0,0,0], 4, (3,4), (1,1), 0.5, (2,3), (0,1), 0.2, StitchingOrder::SourceOnTop, StitchingQuality::Fancy),
+            stitch_appendage(&src, (3,4), &Appendage { buf: &appendage, dimensions: (2,3), joint }, &[0,0,0,0], 4, StitchingQuality::Fancy)
+        );
+    }
+
+    #[test]
+    fn u32_buffers_stitch() {
+        let max = u32::MAX;
+
+        assert_eq!(stitch_pixel(&[0u32,0,max,max], &[max,0,0,max], StitchingOrder::AppendageOnTop, &StitchConfig::default()), Ok(vec![max,0,0,max]));
+    }
+
+    #[test]
+    fn mismatched_buffers_are_rejected() {
+        let joint = Joint::new((0,0), 0.0, (0,0), 0.0, StitchingOrder::AppendageOnTop);
+
+        assert_eq!(fancy_rotate(&[1u8,2,3], &[0], 1, 2, 2, 0.3), Err(ParsingError::BufferSizeMismatch { expected: 4, actual: 3 }));
+        assert_eq!(stitch_appendage(&[0u8; 3], (2,2), &Appendage { buf: &[0u8; 4], dimensions: (2,2), joint }, &[0], 1, StitchingQuality::Fast), Err(ParsingError::BufferSizeMismatch { expected: 4, actual: 3 }));
+        assert_eq!(stitch_appendage(&[0u8; 4], (2,2), &Appendage { buf: &[0u8; 5], dimensions: (2,2), joint }, &[0], 1, StitchingQuality::Fast), Err(ParsingError::BufferSizeMismatch { expected: 4, actual: 5 }));
+    }
+
+    #[test]
+    fn anchor_at_the_width_is_rejected() {
+        let joint = Joint::new((2,0), 0.0, (0,0), 0.0, StitchingOrder::AppendageOnTop);
+
+        assert_eq!(stitch_appendage(&[0u8; 4], (2,2), &Appendage { buf: &[0u8; 4], dimensions: (2,2), joint }, &[0], 1, StitchingQuality::Fast), Err(ParsingError::AnchorOutOfBounds));
+    }
+
+    #[test]
+    fn mismatched_empty_pixel_is_rejected() {
+        let buf = [0u8; 2*2*3];
+
+        assert_eq!(fast_rotate(&buf, &[0,0,0,0], 3, 2, 2, 0.3), Err(ParsingError::EmptyPixelMismatch { expected: 3, actual: 4 }));
+        assert_eq!(fancy_rotate(&buf, &[0,0,0,0], 3, 2, 2, 0.3), Err(ParsingError::EmptyPixelMismatch { expected: 3, actual: 4 }));
+    }
+
+    #[test]
+    fn zero_channels_are_rejected() {
+        assert_eq!(fast_rotate::<u8>(&[], &[], 0, 2, 2, 0.3), Err(ParsingError::ZeroChannels));
+        assert_eq!(fancy_rotate::<u8>(&[1], &[], 0, 2, 2, 0.3), Err(ParsingError::ZeroChannels));
+    }
+
+    #[test]
+    fn quarter_turns_permute_pixels() {
+        let buf: Vec<u8> = (0..6).collect();
+
+        assert_eq!(fast_rotate(&buf, &[9], 1, 3, 2, std::f64::consts::FRAC_PI_2), Ok((2,3,vec![2,5, 1,4, 0,3])));
+        assert_eq!(fast_rotate(&buf, &[9], 1, 3, 2, std::f64::consts::PI), Ok((3,2,vec![5,4,3, 2,1,0])));
+        assert_eq!(fast_rotate(&buf, &[9], 1, 3, 2, -std::f64::consts::FRAC_PI_2), Ok((2,3,vec![3,0, 4,1, 5,2])));
+    }
+
+    #[test]
+    fn inverse_rotate_point_round_trips() {
+        let (width, height) = (9, 5);
+
+        for angle in [0.2, 0.9, -1.3, 2.8] {
+            let (new_width, new_height, _) = fast_rotate(&[1u8; 9*5], &[0], 1, width, height, angle).unwrap();
+
+            for y in 1..height-1 {
+                for x in 1..width-1 {
+                    let rotated = rotate_point((x,y), width, height, (new_width, new_height), angle);
+                    let (back_x, back_y) = inverse_rotate_point(rotated, width, height, (new_width, new_height), angle).unwrap();
+                    assert!((back_x - x as f64).abs() <= 1.0 && (back_y - y as f64).abs() <= 1.0, "{},{} came back as {},{} at {}", x, y, back_x, back_y, angle);
+                }
+            }
+        }
+    }
+
+    /// Stitches a 1x1 appendage onto a 1x1 source, returning the stitched pixel
+    /// Stitches a 1x1 appendage onto a 1x1 source, returning the stitched pixel
+    fn stitch_pixel<T>(src: &[T], appendage: &[T], order: StitchingOrder, config: &StitchConfig<T>) -> Result<Vec<T>, ParsingError>
+    where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+    {
+        let empty = vec![T::from_f64(0.0); src.len()];
+        let appendage = Appendage { buf: appendage, dimensions: (1,1), joint: Joint::new((0,0), 0.0, (0,0), 0.0, order) };
+
+        Ok(stitch_with_config(src, (1,1), &appendage, &empty, src.len(), StitchingQuality::Fast, config)?.0.2)
+    }
+
+    #[test]
+    fn stitch_blends_with_each_mode() {
+        let blend = |top: [u8; 4], bottom: [u8; 4], blend_mode| stitch_pixel(&bottom, &top, StitchingOrder::AppendageOnTop, &StitchConfig { blend_mode, ..StitchConfig::default() });
+
+        assert_eq!(blend([255,0,0,128], [0,0,255,255], BlendMode::SourceOver), Ok(vec![128,0,127,255]));
+        assert_eq!(blend([128,255,0,255], [255,128,0,255], BlendMode::Multiply), Ok(vec![128,128,0,255]));
+        assert_eq!(blend([128,0,0,255], [128,255,0,255], BlendMode::Screen), Ok(vec![192,255,0,255]));
+        assert_eq!(blend([200,100,0,255], [100,100,0,255], BlendMode::Additive), Ok(vec![255,200,0,255]));
+        assert_eq!(blend([1,2,3,1], [9,9,9,255], BlendMode::Replace), Ok(vec![1,2,3,1]));
+    }
+
+    #[test]
+    fn opaque_appendage_shows_through_empty_canvas_beneath_source() {
+        let (src, empty) = ([9u8; 2*2*3], [0u8; 3]);
+        let appendage = [5u8; 6*3];
+        let config = StitchConfig { alpha_channel: AlphaChannel::Opaque, ..StitchConfig::default() };
+
+        let appendage_pixels = |top| {
+            let appendage = Appendage { buf: &appendage, dimensions: (1,6), joint: Joint::new((0,1), 0.0, (0,0), 0.0, top) };
+            let ((width, height, result), _) = stitch_with_config(&src, (2,2), &appendage, &empty, 3, StitchingQuality::Fast, &config).unwrap();
+            assert_eq!((width, height), (2,7));
+            result.chunks(3).filter(|pixel| *pixel == [5,5,5]).count()
+        };
+
+        assert_eq!(appendage_pixels(StitchingOrder::AppendageOnTop), 6);
+        // The first appendage pixel lies on the source, which hides it
+        assert_eq!(appendage_pixels(StitchingOrder::SourceOnTop), 5);
+    }
+
+    #[test]
+    fn alpha_may_be_the_first_channel() {
+        let config = StitchConfig { alpha_channel: AlphaChannel::Index(0), ..StitchConfig::default() };
+
+        // ARGB, half transparent red over opaque blue
+        assert_eq!(stitch_pixel(&[255u8,0,0,255], &[128,255,0,0], StitchingOrder::AppendageOnTop, &config), Ok(vec![255,128,0,127]));
+    }
+
+    /// Composites a 2x2 image of gray+alpha pixels 1 2 / 3 4 onto an empty 3x3 destination, returning the gray values
+    fn composite_gray(offset: (isize,isize), alpha_channel: AlphaChannel) -> Result<Vec<u8>, ParsingError> {
+        let src: Vec<u8> = (1..=4).flat_map(|value| [value, 255]).collect();
+        let mut dest = vec![0u8; 3*3*2];
+
+        composite_onto(&mut dest, (3,3), ImageView::new(&src, 2, 2, 2)?, offset, BlendMode::SourceOver, alpha_channel)?;
+        Ok(dest.chunks(2).map(|pixel| pixel[0]).collect())
+    }
+
+    #[test]
+    fn composite_onto_clips_on_all_four_edges() {
+        assert_eq!(composite_gray((0,0), AlphaChannel::Last), Ok(vec![1,2,0, 3,4,0, 0,0,0]));
+        assert_eq!(composite_gray((-1,0), AlphaChannel::Last), Ok(vec![2,0,0, 4,0,0, 0,0,0]));
+        assert_eq!(composite_gray((0,-1), AlphaChannel::Last), Ok(vec![3,4,0, 0,0,0, 0,0,0]));
+        assert_eq!(composite_gray((2,0), AlphaChannel::Last), Ok(vec![0,0,1, 0,0,3, 0,0,0]));
+        assert_eq!(composite_gray((0,2), AlphaChannel::Last), Ok(vec![0,0,0, 0,0,0, 1,2,0]));
+        assert_eq!(composite_gray((-2,0), AlphaChannel::Last), Err(ParsingError::NoOverlap));
+        assert_eq!(composite_gray((3,0), AlphaChannel::Last), Err(ParsingError::NoOverlap));
+    }
+
+    #[test]
+    fn composite_onto_handles_extreme_offsets() {
+        for offset in [(isize::MIN,0), (0,isize::MIN), (isize::MAX,0), (0,isize::MAX), (isize::MIN,isize::MAX)] {
+            assert_eq!(composite_gray(offset, AlphaChannel::Last), Err(ParsingError::NoOverlap));
+        }
+    }
+
+    #[test]
+    fn composite_onto_uses_the_given_alpha_channel() {
+        // Fully transparent if channel 0 is the alpha, fully opaque if channel 1 is
+        let src = [0u8, 255];
+        let composite = |alpha_channel| {
+            let mut dest = [200u8, 100];
+            composite_onto(&mut dest, (1,1), ImageView::new(&src, 1, 1, 2).unwrap(), (0,0), BlendMode::SourceOver, alpha_channel).unwrap();
+            dest
+        };
+
+        assert_eq!(composite(AlphaChannel::Index(0)), [200, 100]);
+        assert_eq!(composite(AlphaChannel::Last), [0, 255]);
+        assert_eq!(composite(AlphaChannel::Opaque), [0, 255]);
+    }
+
+    #[test]
+    fn linear_compositing_is_brighter_at_an_edge() {
+        let composite = |gamma_correct| stitch_pixel(&[0u8,0,0,255], &[255,0,0,128], StitchingOrder::AppendageOnTop, &StitchConfig { gamma_correct, ..StitchConfig::default() }).unwrap();
+        let (plain, linear) = (composite(false), composite(true));
+
+        assert_eq!(plain, [128,0,0,255]);
+        assert_eq!(linear, [188,0,0,255]);
+    }
+
+    #[test]
+    fn full_turns_are_ignored() {
+        let buf: Vec<u8> = (0..5*3*2).map(|value| value as u8).collect();
+
+        for angle in [0.3, -1.2, 2.5, std::f64::consts::FRAC_PI_2, std::f64::consts::PI] {
+            assert_eq!(fast_rotate(&buf, &[0,0], 2, 5, 3, angle), fast_rotate(&buf, &[0,0], 2, 5, 3, angle + 2.0*std::f64::consts::PI), "angle {}", angle);
+        }
+    }
+
+    #[test]
+    fn opacity_fades_the_appendage() {
+        let composite = |opacity| stitch_pixel(&[0u8,0,255,255], &[255,0,0,255], StitchingOrder::AppendageOnTop, &StitchConfig { opacity, ..StitchConfig::default() });
+
+        assert_eq!(composite(0.0), Ok(vec![0,0,255,255]));
+        assert_eq!(composite(1.0), Ok(vec![255,0,0,255]));
+    }
+
+    #[test]
+    fn magenta_color_key_counts_as_empty() {
+        let magenta = [255u8,0,255];
+        let config = StitchConfig { alpha_channel: AlphaChannel::Opaque, color_key: Some(&magenta[..]), ..StitchConfig::default() };
+        // Red and magenta
+        let src = [255u8,0,0, 255,0,255];
+
+        let stitched = |appendage: &[u8], anchor, order| {
+            let appendage = Appendage { buf: appendage, dimensions: (1,1), joint: Joint::new(anchor, 0.0, (0,0), 0.0, order) };
+            stitch_with_config(&src, (2,1), &appendage, &magenta, 3, StitchingQuality::Fast, &config).unwrap().0.2
+        };
+
+        assert_eq!(stitched(&magenta, (0,0), StitchingOrder::AppendageOnTop), src);
+        assert_eq!(stitched(&[0,255,0], (1,0), StitchingOrder::SourceOnTop), [255,0,0, 0,255,0]);
+    }
+
+    #[test]
+    fn mask_counts_the_non_empty_pixels() {
+        let buf: Vec<u8> = (0..5*4).flat_map(|index| [(index*13%7) as u8 + 1, 1, 2, 255]).collect();
+
+        for quality in [StitchingQuality::Fast, StitchingQuality::Fancy] {
+            let ((width, height, rotated), mask) = rotate_with_mask(&buf, &[0,0,0,0], 4, 5, 4, 0.5, quality).unwrap();
+
+            assert_eq!(mask.len(), width*height);
+            assert_eq!(mask.iter().filter(|value| **value == 255).count(), rotated.chunks(4).filter(|pixel| *pixel != [0,0,0,0]).count());
+        }
+    }
+
+    #[test]
+    fn relative_center_is_the_center_pixel() {
+        assert_eq!(Anchor::Relative((0.5,0.5)).to_pixel((5,5)), Ok((2,2)));
+    }
+
+    #[test]
+    fn wrap_connects_opposite_edges() {
+        let buf: Vec<u8> = (1..=9).collect();
+        let angle = 0.3f64;
+        let (width, height, rotated) = fast_rotate_with_edges(&buf, 1, 3, 3, angle, EdgeMode::Wrap).unwrap();
+
+        // Every pixel comes from the source pixel it maps to, taken modulo the dimensions
+        for (index, value) in rotated.iter().enumerate() {
+            let x = (index%width) as f64 + 0.5 - width as f64/2.0;
+            let y = (index/width) as f64 + 0.5 - height as f64/2.0;
+            let source_x = (x*angle.cos() - y*angle.sin() + 1.0).round() as i64;
+            let source_y = (x*angle.sin() + y*angle.cos() + 1.0).round() as i64;
+
+            assert_eq!(*value, buf[(source_y.rem_euclid(3)*3 + source_x.rem_euclid(3)) as usize]);
+        }
+    }
+
+    #[test]
+    fn rgba_appendage_over_rgb_source() {
+        let src = [10u8,20,30].repeat(4*4);
+        let mut appendage = [200u8,0,0,255].repeat(2*2);
+        appendage[3] = 0;
+        let joint = Joint::new((1,1), 0.0, (0,0), 0.0, StitchingOrder::AppendageOnTop);
+
+        let (width, _, stitched) = stitch_mixed_channels(&src, 3, (4,4), &Appendage { buf: &appendage, dimensions: (2,2), joint }, 4, &[0,0,0,0], StitchingQuality::Fast).unwrap();
+        // The transparent pixel leaves the source as is, with an opaque alpha
+        assert_eq!(stitched[(width+1)*4..(width+1)*4+4], [10,20,30,255]);
+        assert_eq!(stitched[(width+2)*4..(width+2)*4+4], [200,0,0,255]);
+    }
+
+    #[test]
+    fn smooth_rotation_interpolates_edges() {
+        let buf = vec![200u8; 8*6];
+
+        let (_, _, smooth) = smooth_rotate(&buf, &[0], 1, 8, 6, 0.5).unwrap();
+        let (_, _, fast) = fast_rotate(&buf, &[0], 1, 8, 6, 0.5).unwrap();
+        assert!(smooth.iter().any(|value| *value > 0 && *value < 200));
+        assert!(fast.iter().all(|value| *value == 0 || *value == 200));
+    }
+
+    #[test]
+    fn rotate_points_matches_rotate_point() {
+        let points = [(0,0), (6,0), (6,2), (0,2), (3,1)];
+
+        for angle in [0.0, 0.4, std::f64::consts::FRAC_PI_2, -1.3, 3.0, 10.0] {
+            let (rotated_width, rotated_height, _) = fast_rotate(&[0u8; 7*3], &[0], 1, 7, 3, angle).unwrap();
+            let single: Vec<(usize,usize)> = points.iter().map(|point| rotate_point(*point, 7, 3, (rotated_width, rotated_height), angle)).collect();
+
+            assert_eq!(rotate_points(&points, 7, 3, angle), single, "angle {}", angle);
+        }
+    }
+
+    #[test]
+    fn rotated_corners_stay_inside_the_image() {
+        for (width, height) in [(5,3), (9,6), (1,7), (13,13)] {
+            for step in 0..64 {
+                let angle = step as f64*0.1 - 3.2;
+                let (new_width, new_height, _) = fast_rotate(&vec![1u8; width*height], &[0], 1, width, height, angle).unwrap();
+
+                for corner in [(0,0), (width-1,0), (0,height-1), (width-1,height-1)] {
+                    let rotated = rotate_point(corner, width, height, (new_width, new_height), angle);
+                    assert!(rotated.0 < new_width && rotated.1 < new_height, "{:?} of {}x{} lands on {:?} at {}", corner, width, height, rotated, angle);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn clipping_crops_the_appendage_at_the_frame_edge() {
+        let frame = [10u8,10,10,255].repeat(6*6);
+        let sword = [200u8,0,0,255].repeat(8*2);
+        let config = StitchConfig { clip_to_source: true, ..StitchConfig::default() };
+        let appendage = Appendage { buf: &sword, dimensions: (8,2), joint: Joint::new((3,3), 0.0, (0,0), 0.0, StitchingOrder::AppendageOnTop) };
+
+        let ((width, height, stitched), anchor) = stitch_with_config(&frame, (6,6), &appendage, &[0,0,0,0], 4, StitchingQuality::Fast, &config).unwrap();
+        assert_eq!((width, height, anchor), (6, 6, (3,3)));
+        for (index, pixel) in stitched.chunks(4).enumerate() {
+            let (x, y) = (index%6, index/6);
+            let expected = if (y == 3 || y == 4) && x >= 3 { [200,0,0,255] } else { [10,10,10,255] };
+            assert_eq!(pixel, expected, "pixel {},{}", x, y);
+        }
+    }
+
+    #[test]
+    fn u32_blending_keeps_its_precision() {
+        let max = u32::MAX;
+        let (src, appendage) = ([max-1, max-3, 5, max], [max-2, 7, max-5, max/2+1]);
+        let blended = stitch_pixel(&src, &appendage, StitchingOrder::AppendageOnTop, &StitchConfig::default()).unwrap();
+
+        let alpha = appendage[3] as f64/max as f64;
+        for c in 0..3 {
+            let expected = appendage[c] as f64*alpha + src[c] as f64*(1.0-alpha);
+            assert!((blended[c] as f64 - expected).abs() <= 1.0, "channel {} is {} instead of {}", c, blended[c], expected);
+        }
+        assert_eq!(blended[3], max);
+    }
+
+    #[test]
+    fn blend_modes_at_each_alpha() {
+        let bottom = [100u8, 200, 50, 255];
+        let modes = [BlendMode::SourceOver, BlendMode::Multiply, BlendMode::Screen, BlendMode::Additive, BlendMode::Replace];
+        // What each mode mixes a top and bottom value into, before the alpha of the top is applied
+        let mix = |mode, top: f64, bottom: f64| match mode {
+            BlendMode::Multiply => top*bottom/255.0,
+            BlendMode::Screen => top + bottom - top*bottom/255.0,
+            BlendMode::Additive => f64::min(top + bottom, 255.0),
+            _ => top
+        };
+
+        for mode in modes {
+            assert_eq!(blend_pixel(&[200,100,250,0], &bottom, mode, Some(3)), bottom, "{:?}", mode);
+
+            for alpha in [128u8, 255] {
+                let top = [200u8, 100, 250, alpha];
+                let blended = blend_pixel(&top, &bottom, mode, Some(3));
+                let opacity = if mode == BlendMode::Replace { 1.0 } else { alpha as f64/255.0 };
+
+                for c in 0..3 {
+                    let expected = mix(mode, top[c] as f64, bottom[c] as f64)*opacity + bottom[c] as f64*(1.0-opacity);
+                    assert!((blended[c] as f64 - expected).abs() <= 0.5001, "{:?} at alpha {} gives {} instead of {}", mode, alpha, blended[c], expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn anchor_on_the_rounding_edge_stitches() {
+        // 135 degrees on an odd sized image puts the rotated anchor right on the rounding edge
+        let appendage = [1u8; 3*5*4];
+        let joint = Joint::new((0,0), 3.0*std::f64::consts::FRAC_PI_4, (2,4), 0.0, StitchingOrder::AppendageOnTop);
+        let (width, height, stitched) = stitch_appendage(&[2u8; 4], (1,1), &Appendage { buf: &appendage, dimensions: (3,5), joint }, &[0,0,0,0], 4, StitchingQuality::Fast).unwrap();
+
+        assert_eq!(stitched.len(), width*height*4);
+    }
+
+    #[test]
+    fn rotation_transform_maps_center_to_center() {
+        for angle in [0.0f64, 17.0, 45.0, 90.0, 133.0, -60.0].map(f64::to_radians) {
+            let ((new_width, new_height), forward, _) = rotation_transform(7, 3, angle);
+            let center = (forward[0]*3.5 + forward[1]*1.5 + forward[2], forward[3]*3.5 + forward[4]*1.5 + forward[5]);
+
+            assert!((center.0 - new_width as f64/2.0).abs() < 1e-9 && (center.1 - new_height as f64/2.0).abs() < 1e-9, "angle {}", angle);
+        }
+    }
+
+    #[test]
+    fn blend_order_combines_half_alpha_layers() {
+        let (red, blue) = ([200u8,0,0,128], [0u8,0,200,128]);
+
+        assert_eq!(stitch_pixel(&red, &blue, StitchingOrder::Blend, &StitchConfig::default()), Ok(vec![100,0,100,192]));
+        assert_eq!(stitch_pixel(&blue, &red, StitchingOrder::Blend, &StitchConfig::default()), Ok(vec![100,0,100,192]));
+    }
+
+    #[test]
+    fn gray_rotation_matches_the_general_path() {
+        let buf: Vec<u8> = (0..13*7).map(|value| (value*37%251) as u8).collect();
+
+        for angle in [0.0, 0.3, std::f64::consts::FRAC_PI_4, std::f64::consts::FRAC_PI_2, 2.2, -0.9] {
+            assert_eq!(rotate_gray(&buf, 13, 7, angle, EdgeMode::Fill(&[9])), fast_rotate(&buf, &[9], 1, 13, 7, angle), "angle {}", angle);
+        }
+    }
+
+    #[test]
+    fn half_opaque_mask_matches_embedded_alpha() {
+        let src: Vec<u8> = (0..4*4*4).map(|index| if index%4 == 3 { 255 } else { (index*5) as u8 }).collect();
+        let color: Vec<u8> = (0..3*2*3).map(|index| (200 - index*3) as u8).collect();
+        let mask = [128u8; 3*2];
+        let rgba = add_alpha_mask(&color, 3, 2, 3, &mask).unwrap().2;
+        let joint = Joint::new((1,1), 0.5, (0,0), 0.0, StitchingOrder::AppendageOnTop);
+
+        let embedded = stitch_appendage(&src, (4,4), &Appendage { buf: &rgba, dimensions: (3,2), joint }, &[0,0,0,0], 4, StitchingQuality::Fast);
+        let masked = stitch_masked(&src, (4,4), &Appendage { buf: &color, dimensions: (3,2), joint }, Some(&mask), &[0,0,0,0], 4, StitchingQuality::Fast);
+        assert_eq!(masked, embedded);
+    }
+
+    #[test]
+    fn additive_layers_saturate() {
+        let layer = Image::new(vec![100u8; 2*2*3], 2, 2, 3).unwrap();
+        let summed = composite_additive(&[layer.clone(), layer.clone(), layer], AlphaChannel::Opaque).unwrap();
+
+        assert!(summed.data().iter().all(|value| *value == 255));
+    }
+
+    #[test]
+    fn zero_rotation_is_byte_identical() {
+        let buf: Vec<u8> = (0..5*3*4).map(|value| (value*31%251) as u8).collect();
+
+        assert_eq!(fast_rotate(&buf, &[0,0,0,0], 4, 5, 3, 0.0), Ok((5,3,buf.clone())));
+        assert_eq!(fancy_rotate(&buf, &[0,0,0,0], 4, 5, 3, 0.0), Ok((5,3,buf)));
+    }
+
+    #[test]
+    fn floor_samples_left_of_round_on_a_diagonal_edge() {
+        // Every pixel holds its x coordinate + 1
+        let buf: Vec<u8> = (0..8*8).map(|index| (index%8 + 1) as u8).collect();
+        let round = fast_rotate_with_rounding(&buf, &[0], 1, 8, 8, 0.3, RoundingPolicy::Round).unwrap().2;
+        let floor = fast_rotate_with_rounding(&buf, &[0], 1, 8, 8, 0.3, RoundingPolicy::Floor).unwrap().2;
+
+        let both: Vec<(u8, u8)> = floor.iter().zip(round.iter()).filter(|(floor, round)| **floor != 0 && **round != 0).map(|(floor, round)| (*floor, *round)).collect();
+        assert!(both.iter().all(|(floor, round)| floor <= round));
+        assert!(both.iter().any(|(floor, round)| floor < round));
+    }
+
+    #[test]
+    fn stitched_anchor_matches_rotate_point() {
+        let appendage: Vec<u8> = (0..7*3).flat_map(|value| [value as u8, 255]).collect();
+        let (src, empty) = ([1u8, 255], [0u8, 0]);
+
+        for angle in [0.3, 1.0, -2.2, 4.0, 100.0] {
+            let (rotated_width, rotated_height, _) = fast_rotate(&appendage, &empty, 2, 7, 3, angle).unwrap();
+            let expected = rotate_point((5,1), 7, 3, (rotated_width, rotated_height), angle);
+
+            let joint = Joint::new((0,0), angle, (5,1), 0.0, StitchingOrder::AppendageOnTop);
+            let (_, anchor) = stitch_with_anchor(&src, (1,1), &Appendage { buf: &appendage, dimensions: (7,3), joint }, &empty, 2, StitchingQuality::Fast).unwrap();
+            assert_eq!(anchor, expected, "angle {}", angle);
+        }
+    }
+
+    #[test]
+    fn stitch_all_stacks_appendages_in_the_order_of_the_vector() {
+        // Opaque gray+alpha rows, a 1x1 source with gray 10 and appendages that all hang off its only pixel
+        fn appendage(buf: &[u8], anchor: (usize, usize), order: StitchingOrder) -> Appendage<'_, u8> {
+            Appendage { buf, dimensions: (buf.len()/2,1), joint: Joint::new((0,0), 0.0, anchor, 0.0, order) }
+        }
+        let (first, second, third) = ([20u8, 255].repeat(2), [30u8, 255].repeat(3), [40u8, 255].repeat(2));
+
+        let appendages = vec![
+            appendage(&first, (0,0), StitchingOrder::SourceOnTop),
+            // Goes beneath the source and the first appendage, so only its last pixel shows
+            appendage(&second, (0,0), StitchingOrder::SourceOnTop),
+            // Covers the source and grows the canvas to the left
+            appendage(&third, (1,0), StitchingOrder::AppendageOnTop)
+        ];
+        let ((width, height, stitched), anchors) = stitch_all(&[10u8, 255], &[0,0], 2, (1,1), appendages, StitchingQuality::Fast, &StitchConfig::default()).unwrap();
+
+        assert_eq!((width, height), (4,1));
+        assert_eq!(stitched.chunks(2).map(|pixel| pixel[0]).collect::<Vec<u8>>(), [40, 40, 20, 30]);
+        assert_eq!(anchors, [(1,0); 3]);
+    }
+
+    #[test]
+    fn stitch_all_reads_the_alpha_channel_from_the_config() {
+        // Alpha first, so the appendage is fully transparent
+        let appendage = Appendage { buf: &[0u8, 50], dimensions: (1,1), joint: Joint::new((0,0), 0.0, (0,0), 0.0, StitchingOrder::AppendageOnTop) };
+        let config = StitchConfig { alpha_channel: AlphaChannel::Index(0), ..StitchConfig::default() };
+
+        let (stitched, _) = stitch_all(&[255u8, 10], &[0,0], 2, (1,1), vec![appendage], StitchingQuality::Fast, &config).unwrap();
+        assert_eq!(stitched, (1,1,vec![255, 10]));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{check_buffer, StitchingResult};
+
+/// Rotates an image by exactly 90 degrees anticlockwise.
+/// The pixels are only moved around, so the result is exact and has dimensions height x width
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+pub fn rotate_90<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+
+    Ok((height,width,permute(buf, channels, width, height, width, |x, y| (width-1-y, x))))
+}
+
+/// Rotates an image by exactly 180 degrees.
+/// The pixels are only moved around, so the result is exact and has the same dimensions
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+pub fn rotate_180<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+
+    Ok((width,height,permute(buf, channels, width, width, height, |x, y| (width-1-x, height-1-y))))
+}
+
+/// Rotates an image by exactly 270 degrees anticlockwise (90 degrees clockwise).
+/// The pixels are only moved around, so the result is exact and has dimensions height x width
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+pub fn rotate_270<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+
+    Ok((height,width,permute(buf, channels, width, height, width, |x, y| (y, height-1-x))))
+}
+
+/// Mirrors an image left to right, useful for making a sprite face the other way
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+pub fn flip_horizontal<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+
+    Ok((width,height,permute(buf, channels, width, width, height, |x, y| (width-1-x, y))))
+}
+
+/// Mirrors an image top to bottom
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+pub fn flip_vertical<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+
+    Ok((width,height,permute(buf, channels, width, width, height, |x, y| (x, height-1-y))))
+}
+
+/// Swaps the rows and columns of an image, mirroring it along its main diagonal.
+/// The result has dimensions height x width, which also converts between row major and column major pixel order
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+pub fn transpose<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+
+    Ok((height,width,permute(buf, channels, width, height, width, |x, y| (y, x))))
+}
+
+/// Builds an image of new_width x new_height where every pixel is copied from the source pixel given by source(x, y)
+fn permute<T: Clone>(buf: &[T], channels: usize, width: usize, new_width: usize, new_height: usize, source: impl Fn(usize, usize) -> (usize, usize)) -> Vec<T> {
+    let mut result_buffer: Vec<T> = Vec::with_capacity(new_width*new_height*channels);
+
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let (source_x, source_y) = source(x, y);
+            let index = (source_y*width+source_x)*channels;
+            result_buffer.extend_from_slice(&buf[index..index+channels]);
+        }
+    }
+
+    result_buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flips_mirror_an_asymmetric_image() {
+        // 3x2 with 2 channels, every value distinct
+        let buf: Vec<u8> = (0..12).collect();
+
+        assert_eq!(flip_horizontal(&buf, 3, 2, 2), Ok((3,2,vec![4,5, 2,3, 0,1, 10,11, 8,9, 6,7])));
+        assert_eq!(flip_vertical(&buf, 3, 2, 2), Ok((3,2,vec![6,7, 8,9, 10,11, 0,1, 2,3, 4,5])));
+    }
+
+    #[test]
+    fn transpose_swaps_rows_and_columns() {
+        // 2x3 with 2 channels
+        let buf: Vec<u8> = vec![1,11, 2,12, 3,13, 4,14, 5,15, 6,16];
+        let transposed = transpose(&buf, 2, 3, 2).unwrap();
+
+        assert_eq!(transposed, (3,2,vec![1,11, 3,13, 5,15, 2,12, 4,14, 6,16]));
+        assert_eq!(transpose(&transposed.2, 3, 2, 2), Ok((2,3,buf)));
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{buffer_len, check_buffer, ParsingError, StitchableType, StitchingResult};
+
+/// Paints an outline of the given color around the visible content of an image, the alpha channel is the last channel
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - color - Color of the outline
+/// - thickness - Width of the outline in pixels
+///
+/// Every fully transparent pixel within thickness pixels of a pixel with a non zero alpha is painted,
+/// distances are measured between pixel centers with half a pixel of slack so that a 1px outline covers the diagonals too.
+/// The canvas is grown by thickness on every side to make room, so the image ends up at (thickness, thickness) in the result
+pub fn outline<T>(buf: &[T], width: usize, height: usize, channels: usize, color: &[T], thickness: usize) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+    if color.len() != channels {
+        return Err(ParsingError::ChannelMismatch)
+    }
+
+    let overflow = || ParsingError::DimensionsTooLarge;
+    let margin = thickness.checked_mul(2).ok_or_else(overflow)?;
+    let new_width = width.checked_add(margin).ok_or_else(overflow)?;
+    let new_height = height.checked_add(margin).ok_or_else(overflow)?;
+    buffer_len(new_width, new_height, channels)?;
+
+    let alpha_index = channels-1;
+    let visible = |x: usize, y: usize| buf[(y*width+x)*channels+alpha_index].to_f64() > 0.0;
+
+    let reach = (thickness as f64 + 0.5).powi(2);
+    let transparent = vec![T::from_f64(0.0); channels];
+    let mut result_buffer: Vec<T> = Vec::with_capacity(new_width*new_height*channels);
+
+    let radius = thickness as isize;
+
+    for y in 0..new_height {
+        for x in 0..new_width {
+            // Position of this pixel in the source image, which may lie outside of it
+            let center = (x as isize - radius, y as isize - radius);
+            let inside = center.0 >= 0 && center.1 >= 0 && (center.0 as usize) < width && (center.1 as usize) < height;
+
+            if inside && visible(center.0 as usize, center.1 as usize) {
+                let index = (center.1 as usize*width+center.0 as usize)*channels;
+                result_buffer.extend_from_slice(&buf[index..index+channels]);
+                continue
+            }
+
+            let near_content = (isize::max(center.1-radius, 0)..=isize::min(center.1+radius, height as isize-1)).any(|sy| {
+                (isize::max(center.0-radius, 0)..=isize::min(center.0+radius, width as isize-1)).any(|sx| {
+                    let (dx, dy) = ((sx-center.0) as f64, (sy-center.1) as f64);
+                    dx*dx + dy*dy <= reach && visible(sx as usize, sy as usize)
+                })
+            });
+
+            if near_content {
+                result_buffer.extend_from_slice(color);
+            } else if inside {
+                let index = (center.1 as usize*width+center.0 as usize)*channels;
+                result_buffer.extend_from_slice(&buf[index..index+channels]);
+            } else {
+                result_buffer.extend_from_slice(&transparent);
+            }
+        }
+    }
+
+    Ok((new_width,new_height,result_buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outline_of_a_square_is_a_1px_ring() {
+        // A solid 3x3 square inside a transparent 5x5 image
+        let mut buf = vec![0u8; 5*5*4];
+        for y in 1..4 {
+            for x in 1..4 {
+                buf[(y*5+x)*4..(y*5+x)*4+4].copy_from_slice(&[200,0,0,255]);
+            }
+        }
+
+        let (width, height, outlined) = outline(&buf, 5, 5, 4, &[0,0,255,255], 1).unwrap();
+        let rows: Vec<String> = outlined.chunks(width*4).map(|row| row.chunks(4).map(|pixel| match pixel {
+            [200,0,0,255] => 's',
+            [0,0,255,255] => 'o',
+            _ => '.'
+        }).collect()).collect();
+
+        assert_eq!((width, height), (7,7));
+        assert_eq!(rows, [".......", ".ooooo.", ".ossso.", ".ossso.", ".ossso.", ".ooooo.", "......."]);
+    }
+}
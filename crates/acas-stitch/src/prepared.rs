@@ -0,0 +1,71 @@
+use crate::{check_buffer, check_config, check_empty, map_rotated_point, rotate_supersampled, stitch_rotated, supersample, ModeFilter, RotatedAppendage, Rotation};
+use crate::{Joint, ParsingError, PositionedResult, StitchConfig, StitchableType};
+
+/// An appendage that has already been upscaled for the fancy algorithm, created by prepare_appendage()
+///
+/// Stitching the same appendage many times with stitch_with_prepared() skips the upscaling,
+/// which is the most expensive part of fancy_rotate()
+#[derive(Debug, Clone)]
+pub struct PreparedAppendage<T> {
+    supersampled: (usize,usize,Vec<T>),
+    dimensions: (usize,usize),
+    channels: usize
+}
+
+impl<T> PreparedAppendage<T> {
+    /// Dimensions of the original appendage image
+    pub fn dimensions(&self) -> (usize,usize) {
+        self.dimensions
+    }
+
+    /// No. of channels per pixel
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+}
+
+/// Upscales an appendage once so it can be stitched repeatedly with stitch_with_prepared()
+///
+/// # Arguments
+///
+/// - buf, dimensions - The appendage image and its dimensions
+/// - channels - No. of channels per pixel
+pub fn prepare_appendage<T: Clone + std::fmt::Debug>(buf: &[T], channels: usize, dimensions: (usize,usize)) -> Result<PreparedAppendage<T>,ParsingError> where [T]: Eq {
+    check_buffer(buf, channels, dimensions.0, dimensions.1)?;
+
+    Ok(PreparedAppendage {
+        supersampled: supersample(buf, channels, dimensions.0, dimensions.1, 8)?,
+        dimensions,
+        channels
+    })
+}
+
+/// Same as stitch_with_config() with StitchingQuality::Fancy, but takes an appendage prepared by prepare_appendage()
+///
+/// # Arguments
+///
+/// - src, src_dimensions - The source image and its dimensions
+/// - appendage - The prepared appendage
+/// - joint - Where the appendage is attached to the source, see Joint
+/// - empty - Equivalent of empty pixel
+/// - config - Additional options, see StitchConfig
+///
+/// The source must have the same number of channels as the appendage
+pub fn stitch_with_prepared<T>(src: &[T], src_dimensions: (usize,usize), appendage: &PreparedAppendage<T>, joint: Joint, empty: &[T], config: &StitchConfig<T>) -> PositionedResult<T>
+where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+{
+    let channels = appendage.channels;
+
+    check_buffer(src, channels, src_dimensions.0, src_dimensions.1)?;
+    check_empty(empty, channels)?;
+    let src_anchor = joint.src_anchor.to_pixel(src_dimensions)?;
+    let appendage_anchor = joint.appendage_anchor.to_pixel(appendage.dimensions)?;
+    let alpha_index = check_config(config, channels)?;
+
+    let rotation = Rotation::new(joint.src_angle - joint.appendage_angle);
+    let rotated = rotate_supersampled(&appendage.supersampled, empty, channels, &rotation, 8, ModeFilter::packed(config.color_key))?;
+
+    let anchor = map_rotated_point(appendage_anchor, appendage.dimensions.0, appendage.dimensions.1, (rotated.0, rotated.1), &rotation);
+
+    stitch_rotated(src, src_dimensions, RotatedAppendage { image: rotated, anchor, src_anchor, order: joint.order }, empty, channels, config, alpha_index)
+}
@@ -0,0 +1,172 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{Anchor, Image, Joint, ParsingError, StitchableType, StitchingOrder, StitchingQuality};
+
+#[derive(Error, Debug)]
+/// The Types of Errors that can occur when loading rig metadata
+pub enum MetadataError {
+    #[error("Could not read metadata file: {0}")]
+    /// The metadata file could not be opened or read
+    Io(#[from] std::io::Error),
+    #[error("Invalid metadata: {0}")]
+    /// The metadata is not valid JSON or does not match the layout of RigMetadata
+    Json(#[from] serde_json::Error),
+    #[error("Rigs cannot be interpolated, they have a different number of frames or kinds of anchors")]
+    /// Two rigs passed to RigMetadata::tween() don't line up, either their frame counts differ
+    /// or one uses a pixel anchor where the other uses a relative one
+    Mismatch
+}
+
+/// How the progress between two keyframes is spread over the frames in between
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Easing {
+    /// Constant speed
+    #[default]
+    Linear,
+    /// Starts slow and speeds up
+    EaseIn,
+    /// Starts fast and slows down
+    EaseOut,
+    /// Starts and ends slow
+    EaseInOut
+}
+
+impl Easing {
+    /// Maps the linear progress t (0.0..=1.0) to the eased progress, 0.0 and 1.0 are kept as is
+    pub fn ease(&self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t*t,
+            Easing::EaseOut => t*(2.0-t),
+            Easing::EaseInOut => if t < 0.5 { 2.0*t*t } else { 1.0 - 2.0*(1.0-t)*(1.0-t) }
+        }
+    }
+}
+
+/// Where and at what angle a part of a rig is attached
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Placement {
+    /// The point of stitching, e.g. `{"Pixel": [24, 12]}` or `{"Relative": [0.5, 0.5]}`
+    pub anchor: Anchor,
+    /// The angle (in degrees), positive => Anticlockwise
+    pub angle: f64
+}
+
+impl Placement {
+    /// The angle in radians, as the stitching functions expect
+    pub fn angle_radians(&self) -> f64 {
+        self.angle.to_radians()
+    }
+
+    /// The placement a fraction t of the way from this one to another, pixel anchors are rounded to the closest pixel
+    ///
+    /// Returns MetadataError::Mismatch if one anchor is a pixel and the other relative
+    fn interpolate(&self, to: &Placement, t: f64) -> Result<Placement, MetadataError> {
+        let lerp = |from: f64, to: f64| from + (to-from)*t;
+
+        let anchor = match (self.anchor, to.anchor) {
+            (Anchor::Pixel(from), Anchor::Pixel(to)) => Anchor::Pixel((
+                lerp(from.0 as f64, to.0 as f64).round() as usize,
+                lerp(from.1 as f64, to.1 as f64).round() as usize
+            )),
+            (Anchor::Relative(from), Anchor::Relative(to)) => Anchor::Relative((lerp(from.0, to.0), lerp(from.1, to.1))),
+            _ => return Err(MetadataError::Mismatch)
+        };
+
+        Ok(Placement { anchor, angle: lerp(self.angle, to.angle) })
+    }
+}
+
+/// Anchors and angles for stitching one appendage onto every frame of an animation
+///
+/// Read from JSON like
+/// ```json
+/// {
+///     "appendage": { "anchor": {"Pixel": [24, 12]}, "angle": -27.0 },
+///     "frames": [
+///         { "anchor": {"Pixel": [1, 25]}, "angle": -27.0 }
+///     ]
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RigMetadata {
+    /// The point of stitching on the appendage image and the angle it is drawn at in it
+    pub appendage: Placement,
+    /// The point of stitching on each frame and the desired angle of the appendage in it
+    pub frames: Vec<Placement>
+}
+
+impl RigMetadata {
+    /// Reads rig metadata from a JSON file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<RigMetadata, MetadataError> {
+        let file = std::fs::File::open(path)?;
+
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    /// Interpolates between this rig and another one, e.g. to animate a sword swing between two keyframes
+    ///
+    /// # Arguments
+    ///
+    /// - to - The rig at the end of the transition, with as many frames as this one
+    /// - steps - No. of rigs to generate, the first is this rig and the last is to, so 3 steps gives the start, halfway point and end
+    /// - easing - How the progress is spread over the steps
+    ///
+    /// Every placement, the appendage's as well as each frame's, is interpolated on its own.
+    /// Angles are interpolated as they are written, so going from 350 to 10 degrees turns the long way round, write -10 to turn the short way.
+    /// Returns MetadataError::Mismatch if the rigs have different frame counts or matching anchors are of different kinds
+    pub fn tween(&self, to: &RigMetadata, steps: usize, easing: Easing) -> Result<Vec<RigMetadata>, MetadataError> {
+        if self.frames.len() != to.frames.len() {
+            return Err(MetadataError::Mismatch)
+        }
+
+        (0..steps).map(|step| {
+            let t = if steps > 1 { easing.ease(step as f64/(steps-1) as f64) } else { 0.0 };
+
+            Ok(RigMetadata {
+                appendage: self.appendage.interpolate(&to.appendage, t)?,
+                frames: self.frames.iter().zip(to.frames.iter()).map(|(from, to)| from.interpolate(to, t)).collect::<Result<_,_>>()?
+            })
+        }).collect()
+    }
+
+    /// Stitches the appendage onto every frame at the placements of this rig, see Image::stitch()
+    ///
+    /// # Arguments
+    ///
+    /// - frames - The frames of the animation, in the same order as the placements
+    /// - appendage - The appendage image
+    /// - empty - Equivalent of empty pixel
+    /// - top, quality - Passed on to every stitch
+    ///
+    /// Returns the stitched frames. Extra frames or placements are ignored, so only as many frames as the shorter of the two are returned
+    pub fn stitch_frames<T>(&self, frames: &[Image<T>], appendage: &Image<T>, empty: &[T], top: StitchingOrder, quality: StitchingQuality) -> Result<Vec<Image<T>>, ParsingError>
+    where T: StitchableType + std::fmt::Debug, [T]: Eq + std::hash::Hash
+    {
+        frames.iter().zip(self.frames.iter()).map(|(frame, placement)| {
+            let joint = Joint::new(placement.anchor, placement.angle_radians(), self.appendage.anchor, self.appendage.angle_radians(), top);
+            frame.stitch(appendage, joint, empty, quality)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rig(angle: f64) -> RigMetadata {
+        let placement = Placement { anchor: Anchor::Pixel((4,4)), angle };
+        RigMetadata { appendage: placement.clone(), frames: vec![placement] }
+    }
+
+    #[test]
+    fn linear_tween_of_a_quarter_turn() {
+        let tweened = rig(0.0).tween(&rig(90.0), 3, Easing::Linear).unwrap();
+        let angles: Vec<f64> = tweened.iter().map(|rig| rig.frames[0].angle).collect();
+
+        assert_eq!(angles, [0.0, 45.0, 90.0]);
+    }
+}
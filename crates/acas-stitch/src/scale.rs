@@ -0,0 +1,965 @@
+use std::collections::HashMap;
+
+use crate::warp::{sample_bilinear, sample_nearest};
+use crate::{buffer_len, check_buffer, check_color_key, check_empty, filled_canvas, AlphaChannel, ParsingError, Sampling, StitchableType, StitchingResult};
+
+/// Downscales an image by replacing each block of pixels with the most common pixel in it.
+/// Wont introduce any new colors, which makes it suited to pixel art
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - factor - The size of the square block that is merged into one pixel, must be at least 1
+///
+/// The dimensions of the result are width/factor and height/factor, rounded down
+pub fn downscale<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize) -> StitchingResult<T> where [T]: Eq + std::hash::Hash + std::fmt::Debug {
+    downscale_keyed(buf, width, height, channels, factor, None)
+}
+
+/// Same as downscale() but pixels equal to color_key are treated as transparent,
+/// they are ignored when picking the most common pixel of a block unless the whole block is the key
+pub fn downscale_keyed<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize, color_key: Option<&[T]>) -> StitchingResult<T> where [T]: Eq + std::hash::Hash + std::fmt::Debug {
+    mode_blocks(buf, width, height, channels, factor, ModeFilter::generic(color_key))
+}
+
+/// How mode_blocks() compares the pixels of a block
+pub(crate) struct ModeFilter<'a, T> {
+    /// Pixels equal to this are ignored unless the whole block is the key
+    color_key: Option<&'a [T]>,
+    /// Packs a pixel into a u32 key, see StitchableType::pack_pixel()
+    pack: fn(&[T]) -> Option<u32>
+}
+
+// Derived impls would require T: Copy, though only references and a function pointer are copied
+impl<T> Clone for ModeFilter<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ModeFilter<'_, T> {}
+
+impl<'a, T> ModeFilter<'a, T> {
+    /// Compares whole pixels, which works for any subpixel type
+    pub(crate) fn generic(color_key: Option<&'a [T]>) -> ModeFilter<'a, T> {
+        ModeFilter { color_key, pack: |_| None }
+    }
+}
+
+impl<'a, T: StitchableType> ModeFilter<'a, T> {
+    /// Compares pixels packed by StitchableType::pack_pixel() where the subpixel type supports it
+    pub(crate) fn packed(color_key: Option<&'a [T]>) -> ModeFilter<'a, T> {
+        ModeFilter { color_key, pack: T::pack_pixel }
+    }
+}
+
+/// Replaces each block of pixels with the most common pixel in it, see downscale_keyed()
+pub(crate) fn mode_blocks<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize, filter: ModeFilter<T>) -> StitchingResult<T> where [T]: Eq + std::hash::Hash + std::fmt::Debug {
+    check_buffer(buf, channels, width, height)?;
+    check_color_key(filter.color_key, channels)?;
+    if factor == 0 {
+        return Err(ParsingError::ZeroScale)
+    }
+
+    let new_width = (width as f64/factor as f64).floor() as usize;
+    let new_height = (height as f64/factor as f64).floor() as usize;
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(new_width, new_height, channels)?);
+
+    for y in 0..new_height {
+        // let y_component = y*factor*width;
+
+        for x in 0..new_width {
+            // let index = y_component + x*factor*channels;
+
+            result_buffer.extend_from_slice(find_mode(buf, channels, width, (x*factor, y*factor), factor, filter))
+        }
+    }
+
+    Ok((new_width,new_height,result_buffer))
+}
+
+/// Downscales an image by averaging each block of pixels channel by channel.
+/// Unlike the mode based downscale used by fancy_rotate(), this will introduce new colors,
+/// which makes it better suited to smooth/photographic images than to pixel art
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - factor - The size of the square block that is merged into one pixel, must be at least 1
+///
+/// Partial blocks at the right and bottom edges are averaged over the pixels they do contain,
+/// so the output dimensions are width/factor and height/factor rounded up
+pub fn downscale_average<T>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize) -> StitchingResult<T>
+where T: StitchableType
+{
+    average_blocks(buf, width, height, channels, factor, None)
+}
+
+/// Same as downscale_average() but the color channels are weighted by alpha while averaging,
+/// so fully transparent pixels don't drag the colors of the edges towards their own (usually black) color
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - factor - The size of the square block that is merged into one pixel, must be at least 1
+/// - alpha_channel - Which channel holds the alpha value, with AlphaChannel::Opaque this is the same as downscale_average()
+pub fn downscale_average_premultiplied<T>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize, alpha_channel: AlphaChannel) -> StitchingResult<T>
+where T: StitchableType
+{
+    let alpha_index = alpha_channel.index(channels)?;
+
+    average_blocks(buf, width, height, channels, factor, alpha_index)
+}
+
+/// The filter used by downscale_with_filter() to merge blocks of pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DownscaleFilter {
+    /// The most common pixel of each block, see downscale()
+    Mode,
+    /// Like Mode but each block picks among all of its pixels with an ordered (Bayer) dither,
+    /// the more common a pixel is in the block the more often it is picked, which breaks up banding.
+    /// Only pixels present in the block are picked, so no new colors are introduced
+    DitheredMode,
+    /// The average of each block, see downscale_average()
+    Average,
+    /// A gaussian weighted average that reaches past the block, smoother than Average for large factors
+    Gaussian
+}
+
+/// Downscales an image by a factor with the given filter
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - factor - The size of the square block that is merged into one pixel, must be at least 1
+/// - filter - How each block is merged
+/// - alpha_channel - Which channel holds the alpha value, Average and Gaussian weight the colors by it to avoid dark fringes.
+///   Mode compares whole pixels so it is unaffected
+///
+/// Mode and DitheredMode round the dimensions of the result down like downscale(), Average and Gaussian round them up like downscale_average()
+pub fn downscale_with_filter<T>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize, filter: DownscaleFilter, alpha_channel: AlphaChannel) -> StitchingResult<T>
+where T: StitchableType, [T]: Eq + std::hash::Hash + std::fmt::Debug
+{
+    check_buffer(buf, channels, width, height)?;
+    let alpha_index = alpha_channel.index(channels)?;
+    if factor == 0 {
+        return Err(ParsingError::ZeroScale)
+    }
+
+    match filter {
+        DownscaleFilter::Mode => mode_blocks(buf, width, height, channels, factor, ModeFilter::packed(None)),
+        DownscaleFilter::DitheredMode => dithered_mode_blocks(buf, width, height, channels, factor),
+        DownscaleFilter::Average => average_blocks(buf, width, height, channels, factor, alpha_index),
+        DownscaleFilter::Gaussian => gaussian_blocks(buf, width, height, channels, factor, alpha_index)
+    }
+}
+
+/// Averages each block of pixels, weighting the other channels by the one at alpha_index if there is one
+fn average_blocks<T>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize, alpha_index: Option<usize>) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+    if factor == 0 {
+        return Err(ParsingError::ZeroScale)
+    }
+
+    let new_width = (width + factor - 1)/factor;
+    let new_height = (height + factor - 1)/factor;
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(new_width, new_height, channels)?);
+    let mut sums: Vec<f64> = vec![0.0; channels];
+
+    for y in 0..new_height {
+        let y_range = y*factor..usize::min((y+1)*factor, height);
+
+        for x in 0..new_width {
+            let x_range = x*factor..usize::min((x+1)*factor, width);
+            let count = (y_range.len()*x_range.len()) as f64;
+
+            sums.iter_mut().for_each(|sum| *sum = 0.0);
+
+            for sy in y_range.clone() {
+                for sx in x_range.clone() {
+                    let index = (sy*width+sx)*channels;
+                    let weight = alpha_index.map_or(1.0, |alpha| buf[index+alpha].to_f64());
+                    for c in 0..channels {
+                        sums[c] += if Some(c) == alpha_index { buf[index+c].to_f64() } else { buf[index+c].to_f64()*weight };
+                    }
+                }
+            }
+
+            // Colors were summed weighted by alpha, so they are divided by the total alpha instead of the pixel count
+            let total_weight = alpha_index.map_or(count, |alpha| sums[alpha]);
+
+            for (c, sum) in sums.iter().enumerate() {
+                let value = if Some(c) == alpha_index { sum/count } else if total_weight > 0.0 { sum/total_weight } else { 0.0 };
+                result_buffer.push(T::from_f64(value));
+            }
+        }
+    }
+
+    Ok((new_width,new_height,result_buffer))
+}
+
+/// Samples a gaussian kernel around the center of each block, the other channels are weighted by the one at alpha_index if there is one.
+/// Samples past the edge of the image are left out and the remaining weights renormalized
+fn gaussian_blocks<T>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize, alpha_index: Option<usize>) -> StitchingResult<T>
+where T: StitchableType
+{
+    let new_width = (width + factor - 1)/factor;
+    let new_height = (height + factor - 1)/factor;
+
+    // A sigma of half the block keeps most of the weight inside it, the kernel is cut off at 3 sigma
+    let sigma = factor as f64/2.0;
+    let radius = (3.0*sigma).ceil() as isize;
+    let weight_at = |distance: f64| (-distance*distance/(2.0*sigma*sigma)).exp();
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(new_width, new_height, channels)?);
+    let mut sums: Vec<f64> = vec![0.0; channels];
+
+    for y in 0..new_height {
+        let center_y = ((y*factor) as f64 + usize::min(factor, height-y*factor) as f64/2.0) - 0.5;
+
+        for x in 0..new_width {
+            let center_x = ((x*factor) as f64 + usize::min(factor, width-x*factor) as f64/2.0) - 0.5;
+
+            sums.iter_mut().for_each(|sum| *sum = 0.0);
+            let mut total = 0.0;
+
+            for sy in (center_y.round() as isize - radius)..=(center_y.round() as isize + radius) {
+                if sy < 0 || sy as usize >= height {
+                    continue
+                }
+
+                for sx in (center_x.round() as isize - radius)..=(center_x.round() as isize + radius) {
+                    if sx < 0 || sx as usize >= width {
+                        continue
+                    }
+
+                    let weight = weight_at(sx as f64 - center_x)*weight_at(sy as f64 - center_y);
+                    let index = (sy as usize*width+sx as usize)*channels;
+                    let alpha = alpha_index.map_or(1.0, |alpha| buf[index+alpha].to_f64());
+
+                    total += weight;
+                    for c in 0..channels {
+                        sums[c] += if Some(c) == alpha_index { buf[index+c].to_f64()*weight } else { buf[index+c].to_f64()*weight*alpha };
+                    }
+                }
+            }
+
+            // Colors were summed weighted by alpha as well, so they are divided by the weighted alpha instead
+            let color_weight = alpha_index.map_or(total, |alpha| sums[alpha]);
+
+            for (c, sum) in sums.iter().enumerate() {
+                let value = if Some(c) == alpha_index { sum/total } else if color_weight > 0.0 { sum/color_weight } else { 0.0 };
+                result_buffer.push(T::from_f64(value));
+            }
+        }
+    }
+
+    Ok((new_width,new_height,result_buffer))
+}
+
+/// 4x4 Bayer matrix, each entry is the order in which that position switches away from the most common pixel
+const BAYER_4X4: [[usize; 4]; 4] = [
+    [0, 8, 2, 10],
+    [12, 4, 14, 6],
+    [3, 11, 1, 9],
+    [15, 7, 13, 5]
+];
+
+/// Downscales like downscale() but each block picks a pixel with an ordered dither over how often each pixel appears in it
+fn dithered_mode_blocks<T>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize) -> StitchingResult<T>
+where T: Clone, [T]: Eq + std::hash::Hash
+{
+    let new_width = width/factor;
+    let new_height = height/factor;
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(new_width, new_height, channels)?);
+    let mut indices: HashMap<&[T], usize> = HashMap::new();
+    let mut counts: Vec<(&[T], usize)> = Vec::new();
+
+    for y in 0..new_height {
+        for x in 0..new_width {
+            indices.clear();
+            counts.clear();
+
+            // Counted in raster order so ties go to the pixel that appears first, same as find_mode()
+            for sy in y*factor..(y+1)*factor {
+                for sx in x*factor..(x+1)*factor {
+                    let index = (sy*width+sx)*channels;
+                    let pixel = &buf[index..index+channels];
+                    let position = *indices.entry(pixel).or_insert_with(|| {
+                        counts.push((pixel, 0));
+                        counts.len()-1
+                    });
+                    counts[position].1 += 1;
+                }
+            }
+
+            // Stable, so equally common pixels keep their raster order
+            counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+            let threshold = (BAYER_4X4[y%4][x%4] as f64 + 0.5)/16.0*(factor*factor) as f64;
+            let mut covered = 0;
+            let mut picked = counts[0].0;
+            for (pixel, count) in counts.iter() {
+                covered += count;
+                if covered as f64 > threshold {
+                    picked = pixel;
+                    break
+                }
+            }
+
+            result_buffer.extend_from_slice(picked);
+        }
+    }
+
+    Ok((new_width,new_height,result_buffer))
+}
+
+fn find_mode<'a, T>(buf: &'a [T], channels: usize, width: usize, start: (usize,usize), block_size: usize, filter: ModeFilter<T>) -> &'a [T] where [T]: Eq + std::hash::Hash + std::fmt::Debug {
+    let (startx, starty) = start;
+    let color_key = filter.color_key;
+
+    trace!("Called with {}, {}, {}, {}",width,startx,starty,block_size);
+
+    let first = (starty*width+startx)*channels;
+    let first_pixel: &[T] = &buf[first..first+channels];
+
+    // After upscaling most blocks hold a single color, which needs no counting.
+    // If that color is the key the block is all key, and the first pixel is returned either way
+    let uniform = (starty..starty+block_size).all(|y| {
+        let row = (y*width+startx)*channels;
+        buf[row..row+block_size*channels].chunks_exact(channels).all(|pixel| pixel == first_pixel)
+    });
+    if uniform {
+        return first_pixel
+    }
+
+    // RGBA8 is by far the most common format and comparing a u32 is much cheaper than comparing a slice
+    if (filter.pack)(first_pixel).is_some() {
+        return find_mode_packed(buf, channels, width, start, block_size, filter)
+    }
+
+    // A block only holds a handful of colors, so a linear search beats hashing every pixel.
+    // The colors are kept in the order they first appear in raster order, so ties go to the pixel that appears first
+    let mut count: Vec<(&[T], usize)> = Vec::with_capacity(block_size*block_size);
+    // Only stays the first pixel if every pixel in the block is the color key
+    let mut max_val: &[T] = first_pixel;
+    let mut max_count: usize = 0;
+
+    for y in starty..starty+block_size {
+        for x in startx..startx+block_size {
+
+            let index = (y*width+x)*channels;
+            let pixel = &buf[index..index+channels];
+            if color_key == Some(pixel) {
+                continue
+            }
+
+            match count.iter_mut().find(|(color, _)| *color == pixel) {
+                Some((_, n)) => *n += 1,
+                None => count.push((pixel, 1))
+            }
+        }
+    }
+
+    trace!("Counted {:?}",count);
+
+    for (pixel, value) in count {
+        if value > max_count {
+            max_count = value;
+            max_val = pixel;
+        }
+    }
+
+    trace!("Returning {:?}",max_val);
+
+    max_val
+}
+
+/// Same as find_mode() for pixels that filter packs into a u32, which is then what gets counted
+fn find_mode_packed<'a, T>(buf: &'a [T], channels: usize, width: usize, start: (usize,usize), block_size: usize, filter: ModeFilter<T>) -> &'a [T] {
+    let (startx, starty) = start;
+    let color_key = filter.color_key.map(filter.pack);
+
+    // (Packed color, index of its first pixel, count), in the order the colors first appear
+    let mut count: Vec<(Option<u32>, usize, usize)> = Vec::with_capacity(block_size*block_size);
+    let first = (starty*width+startx)*channels;
+    let mut max_val: &[T] = &buf[first..first+channels];
+    let mut max_count: usize = 0;
+
+    for y in starty..starty+block_size {
+        for x in startx..startx+block_size {
+            let index = (y*width+x)*channels;
+            let key = (filter.pack)(&buf[index..index+channels]);
+            if color_key == Some(key) {
+                continue
+            }
+
+            match count.iter_mut().find(|(color, _, _)| *color == key) {
+                Some((_, _, n)) => *n += 1,
+                None => count.push((key, index, 1))
+            }
+        }
+    }
+
+    // Ties go to the pixel that appears first, same as find_mode()
+    for (_, index, value) in count {
+        if value > max_count {
+            max_count = value;
+            max_val = &buf[index..index+channels];
+        }
+    }
+
+    max_val
+}
+
+/// Upscales an image using the Scale2x algorithm, doubling its dimensions.
+/// Wont introduce any new colors as there is no color interpolation
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+pub fn upscale<T>(buf: &[T],width: usize,height: usize,channels: usize) -> StitchingResult<T>
+where T: Clone + std::fmt::Debug, [T]: Eq
+{
+    let mut scaled = Vec::new();
+    let (new_width, new_height) = upscale_into(buf, width, height, channels, &mut scaled)?;
+
+    Ok((new_width,new_height,scaled))
+}
+
+/// Same as upscale() but writes the result into scaled, replacing its contents,
+/// so a buffer can be reused across passes without reallocating. Returns the dimensions of the result
+pub(crate) fn upscale_into<T>(buf: &[T], width: usize, height: usize, channels: usize, scaled: &mut Vec<T>) -> Result<(usize,usize),ParsingError>
+where T: Clone + std::fmt::Debug, [T]: Eq
+{
+    check_buffer(buf, channels, width, height)?;
+
+    let new_width: usize = width.checked_mul(2).ok_or(ParsingError::DimensionsTooLarge)?;
+    let new_height: usize = height.checked_mul(2).ok_or(ParsingError::DimensionsTooLarge)?;
+    buffer_len(new_width, new_height, channels)?;
+
+    // The border handling below needs at least 3 rows and columns, so tiny images are just doubled
+    if width < 3 || height < 3 {
+        scaled.clear();
+        repeat_pixels(buf, width, height, channels, 2, scaled);
+        return Ok((new_width,new_height))
+    }
+
+    let row_offset = width*channels;
+
+    // Every block is written below (center, edges and corners), the fill value only gives the buffer its length
+    scaled.clear();
+    scaled.resize(new_width * new_height * channels, buf[0].clone());
+
+    // Apply the algorithm to the center
+    for y in 1..height as usize - 1 {
+        let source_y_offset = y * row_offset;
+        let scaled_y_offset = y * 2 * new_width * channels;
+
+        let up_offset = source_y_offset - row_offset;
+        let down_offset = source_y_offset + row_offset;
+
+        for x in 1..width as usize - 1 {
+            let pos = source_y_offset + x * channels;
+            apply_scale2x_block(
+                scaled,
+                scaled_y_offset + x * 2 * channels,
+                channels,
+                new_width,
+                (
+                    // Center
+                    &buf[pos..pos+channels],
+                    // Up
+                    &buf[pos-row_offset..pos-row_offset+channels],
+                    // Left
+                    &buf[pos-channels..pos],
+                    // Down
+                    &buf[pos+row_offset..pos+row_offset+channels],
+                    // Right
+                    &buf[pos+channels..pos+2*channels],
+                ),
+            );
+        }
+
+        // Left most column
+        let p = &buf[source_y_offset..source_y_offset+channels];
+        apply_scale2x_block(
+            scaled,
+            scaled_y_offset,
+            channels,
+            new_width,
+            (p, &buf[up_offset..up_offset+channels], p, &buf[down_offset..down_offset+channels], &buf[source_y_offset+channels..source_y_offset+2*channels]),
+        );
+
+        // Right most column
+        let index = source_y_offset+row_offset-channels;
+        let p = &buf[index..index+channels];
+        apply_scale2x_block(
+            scaled,
+            scaled_y_offset + (new_width - 2)*channels,
+            channels,
+            new_width,
+            (p, &buf[source_y_offset-channels..source_y_offset], &buf[index-channels..index], &buf[index+row_offset..index+row_offset+channels], p),
+        );
+    }
+
+    debug!("Now Doing Top and Bottom");
+
+    for x in 1..width - 1 {
+        // Apply the algorithm to the first row
+        let x_offset = x*channels; 
+        let p = &buf[x_offset..x_offset+channels];
+        apply_scale2x_block(
+            scaled,
+            x*channels*2,
+            channels,
+            new_width,
+            (p, p, &buf[x_offset-channels..x_offset], &buf[x_offset+row_offset..x_offset+row_offset+channels], &buf[x_offset+channels..x_offset+2*channels]),
+        );
+
+        // Apply the algorithm to the last row
+        let index = (height - 1) * width * channels + x_offset;
+        let p = &buf[index..index+channels];
+        let scaled_y_this = ((height - 1) * 2) * new_width * channels;
+        apply_scale2x_block(
+            scaled,
+            scaled_y_this + x*channels*2,
+            channels,
+            new_width,
+            (p, &buf[index-row_offset..index-row_offset+channels], &buf[index-channels..index], p, &buf[index+channels..index+2*channels]),
+        );
+    }
+
+    // Apply the algorithms to the corners
+    debug!("Now Doing Corners");
+
+    // Top left corner
+    let p = &buf[0..channels];
+    apply_scale2x_block(scaled, 0, channels, new_width, (p, p, p, &buf[row_offset..row_offset+channels], &buf[channels..2*channels]));
+
+    // Top right corner
+    let x_right = width - 1;
+    let p = &buf[row_offset-channels..row_offset];
+    apply_scale2x_block(
+        scaled,
+        (new_width-2)*channels,
+        channels,
+        new_width,
+        (p, p, &buf[row_offset-2*channels..row_offset-channels], &buf[row_offset*2-channels..row_offset*2], p),
+    );
+
+    // Bottom left corner
+    let y_bottom = (height-1)*width*channels;
+    let p = &buf[y_bottom..y_bottom+channels];
+    apply_scale2x_block(
+        scaled,
+        (new_height - 2)*new_width*channels,
+        channels,
+        new_width,
+        (p, &buf[y_bottom-row_offset..y_bottom-row_offset+channels], p, p, &buf[y_bottom+channels..y_bottom+2*channels]),
+    );
+
+    // Bottom right corner
+    let y_bottom_right = y_bottom + x_right*channels;
+    let p = &buf[y_bottom_right..y_bottom_right+channels];
+    apply_scale2x_block(
+        scaled,
+        ((new_height-2)*new_width+new_width-2)*channels,
+        channels,
+        new_width,
+        (p, &buf[y_bottom_right-row_offset..y_bottom_right-row_offset+channels], &buf[y_bottom_right-channels..y_bottom_right], p, p)
+    );
+
+    Ok((new_width,new_height))
+}
+
+
+/// Upscales an image using the Scale3x algorithm, tripling its dimensions.
+/// Wont introduce any new colors as there is no color interpolation
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+///
+/// Like upscale(), neighbours that fall outside the image are treated as equal to the pixel being scaled
+pub fn upscale3x<T>(buf: &[T],width: usize,height: usize,channels: usize) -> StitchingResult<T>
+where T: Clone + std::fmt::Debug, [T]: Eq
+{
+    check_buffer(buf, channels, width, height)?;
+
+    let new_width = width.checked_mul(3).ok_or(ParsingError::DimensionsTooLarge)?;
+    let new_height = height.checked_mul(3).ok_or(ParsingError::DimensionsTooLarge)?;
+    buffer_len(new_width, new_height, channels)?;
+
+    let mut scaled = vec![buf[0].clone(); new_width * new_height * channels];
+
+    // Fetches the neighbour at the given offset, falling back to the center pixel at the borders
+    let neighbour = |x: usize, y: usize, dx: isize, dy: isize| -> &[T] {
+        let nx = x as isize + dx;
+        let ny = y as isize + dy;
+        let (nx, ny) = if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize { (x, y) } else { (nx as usize, ny as usize) };
+        let index = (ny*width+nx)*channels;
+        &buf[index..index+channels]
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            apply_scale3x_block(
+                &mut scaled,
+                (y*3*new_width + x*3)*channels,
+                channels,
+                new_width,
+                [
+                    neighbour(x, y, -1, -1), neighbour(x, y, 0, -1), neighbour(x, y, 1, -1),
+                    neighbour(x, y, -1, 0), neighbour(x, y, 0, 0), neighbour(x, y, 1, 0),
+                    neighbour(x, y, -1, 1), neighbour(x, y, 0, 1), neighbour(x, y, 1, 1),
+                ],
+            );
+        }
+    }
+
+    Ok((new_width,new_height,scaled))
+}
+
+/// Scales an image up by an integer factor by repeating every pixel in a factor x factor block.
+/// Wont introduce any new colors, and unlike upscale() doesn't smooth any edges, which makes it suited to quick previews
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - factor - How many times larger the result is along each axis, must be at least 1
+pub fn scale_nearest<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+    if factor == 0 {
+        return Err(ParsingError::ZeroScale)
+    }
+
+    let new_width: usize = width.checked_mul(factor).ok_or(ParsingError::DimensionsTooLarge)?;
+    let new_height: usize = height.checked_mul(factor).ok_or(ParsingError::DimensionsTooLarge)?;
+    buffer_len(new_width, new_height, channels)?;
+
+    let mut scaled = Vec::with_capacity(buffer_len(new_width, new_height, channels)?);
+    repeat_pixels(buf, width, height, channels, factor, &mut scaled);
+
+    Ok((new_width,new_height,scaled))
+}
+
+/// Resizes an image to any dimensions, the aspect ratio is not preserved
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - target_width, target_height - Dimensions of the result, must be at least 1
+/// - sampling - How pixels are read from the source image
+pub fn resize_to<T>(buf: &[T], width: usize, height: usize, channels: usize, target_width: usize, target_height: usize, sampling: Sampling) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+    if target_width == 0 || target_height == 0 {
+        return Err(ParsingError::ZeroScale)
+    }
+
+    let mut result_buffer: Vec<T> = Vec::with_capacity(buffer_len(target_width, target_height, channels)?);
+    let mut pixel: Vec<T> = buf[..channels].to_vec();
+
+    let scale_x = width as f64/target_width as f64;
+    let scale_y = height as f64/target_height as f64;
+
+    for y in 0..target_height {
+        // Pixel centers are mapped onto pixel centers, and then kept inside the image
+        let source_y = ((y as f64+0.5)*scale_y-0.5).clamp(0.0, (height-1) as f64);
+
+        for x in 0..target_width {
+            let source_x = ((x as f64+0.5)*scale_x-0.5).clamp(0.0, (width-1) as f64);
+
+            match sampling {
+                Sampling::Nearest => sample_nearest(buf, width, height, channels, source_x, source_y, &mut pixel),
+                Sampling::Bilinear => sample_bilinear(buf, width, height, channels, source_x, source_y, &mut pixel)
+            }
+            result_buffer.extend_from_slice(&pixel);
+        }
+    }
+
+    Ok((target_width,target_height,result_buffer))
+}
+
+/// Scales an image down to fit inside a bounding box while keeping its aspect ratio, e.g. for previews.
+/// Images that already fit are not scaled up
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - max_width, max_height - Dimensions of the bounding box, must be at least 1
+/// - empty - If given, the result is padded with this value to exactly fill the bounding box, with the image centered in it
+///
+/// Whole factors are averaged like downscale_average(), anything left over is interpolated like resize_to() with Sampling::Bilinear
+pub fn thumbnail<T>(buf: &[T], width: usize, height: usize, channels: usize, max_width: usize, max_height: usize, empty: Option<&[T]>) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+    if let Some(empty) = empty {
+        check_empty(empty, channels)?;
+    }
+    if max_width == 0 || max_height == 0 {
+        return Err(ParsingError::ZeroScale)
+    }
+
+    let scale = f64::min(1.0, f64::min(max_width as f64/width as f64, max_height as f64/height as f64));
+    let target_width = ((width as f64*scale).round() as usize).clamp(1, max_width);
+    let target_height = ((height as f64*scale).round() as usize).clamp(1, max_height);
+
+    let mut thumbnail = (width, height, buf.to_vec());
+
+    let factor = usize::min(width/target_width, height/target_height);
+    if factor > 1 {
+        thumbnail = average_blocks(&thumbnail.2, width, height, channels, factor, None)?;
+    }
+    if (thumbnail.0, thumbnail.1) != (target_width, target_height) {
+        thumbnail = resize_to(&thumbnail.2, thumbnail.0, thumbnail.1, channels, target_width, target_height, Sampling::Bilinear)?;
+    }
+
+    let empty = match empty {
+        Some(empty) => empty,
+        None => return Ok(thumbnail)
+    };
+
+    buffer_len(max_width, max_height, channels)?;
+    let mut padded = filled_canvas(empty, max_width*max_height);
+    let offset = ((max_width-target_width)/2, (max_height-target_height)/2);
+
+    for (y, row) in thumbnail.2.chunks_exact(target_width*channels).enumerate() {
+        let start = ((offset.1+y)*max_width+offset.0)*channels;
+        padded[start..start+row.len()].clone_from_slice(row);
+    }
+
+    Ok((max_width,max_height,padded))
+}
+
+/// Scales an image up by repeating every pixel in a factor x factor block, appending the result to scaled
+fn repeat_pixels<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, factor: usize, scaled: &mut Vec<T>) {
+    for y in 0..height*factor {
+        for x in 0..width*factor {
+            let index = ((y/factor)*width+x/factor)*channels;
+            scaled.extend_from_slice(&buf[index..index+channels]);
+        }
+    }
+}
+
+fn apply_scale2x_block<P>(scaled: &mut Vec<P>, pos: usize, channels: usize, width: usize, pixels: (&[P], &[P], &[P], &[P], &[P]))
+where P: Clone + std::fmt::Debug, [P]: Eq
+{   
+    copy_to_vec(scaled, pos, if pixels.2 == pixels.1 && pixels.2 != pixels.3 && pixels.1 != pixels.4 { pixels.1 } else { pixels.0 });
+    copy_to_vec(scaled, pos+channels, if pixels.1 == pixels.4 && pixels.1 != pixels.2 && pixels.4 != pixels.3 { pixels.4 } else { pixels.0 });
+    copy_to_vec(scaled, pos+width*channels, if pixels.3 == pixels.2 && pixels.3 != pixels.4 && pixels.2 != pixels.1 { pixels.2 } else { pixels.0 });
+    copy_to_vec(scaled, pos+(width+1)*channels, if pixels.4 == pixels.3 && pixels.4 != pixels.1 && pixels.3 != pixels.2 { pixels.3 } else { pixels.0 });
+    // scaled[pos..pos+channels] = (if pixels.2 == pixels.1 && pixels.2 != pixels.3 && pixels.1 != pixels.4 { pixels.1 } else { pixels.0 });
+    // scaled[pos+1..pos+1+channels] = (if pixels.1 == pixels.4 && pixels.1 != pixels.2 && pixels.4 != pixels.3 { pixels.4 } else { pixels.0 }).clone();
+    // scaled[pos+width..pos+width+channels] = (if pixels.3 == pixels.2 && pixels.3 != pixels.4 && pixels.2 != pixels.1 { pixels.2 } else { pixels.0 }).clone();
+    // scaled[pos+width+1..pos+width+1+channels] = (if pixels.4 == pixels.3 && pixels.4 != pixels.1 && pixels.3 != pixels.2 { pixels.3 } else { pixels.0 }).clone();
+}
+
+fn copy_to_vec<P: Clone + std::fmt::Debug>(destination: &mut Vec<P>, start_index: usize, source: &[P]) {
+    trace!("Called with {:?} to put at index {} on a vec of len {}",source,start_index,destination.len());
+    for (i,item) in source.iter().enumerate() {
+        destination[start_index+i] = item.clone()
+    }
+}
+
+fn apply_scale3x_block<P>(scaled: &mut Vec<P>, pos: usize, channels: usize, width: usize, pixels: [&[P]; 9])
+where P: Clone + std::fmt::Debug, [P]: Eq
+{
+    // A B C
+    // D E F
+    // G H I
+    let [a, b, c, d, e, f, g, h, i] = pixels;
+    let row = width*channels;
+
+    copy_to_vec(scaled, pos, if d == b && b != f && d != h { d } else { e });
+    copy_to_vec(scaled, pos+channels, if (d == b && b != f && d != h && e != c) || (b == f && b != d && f != h && e != a) { b } else { e });
+    copy_to_vec(scaled, pos+2*channels, if b == f && b != d && f != h { f } else { e });
+    copy_to_vec(scaled, pos+row, if (d == b && b != f && d != h && e != g) || (d == h && d != b && h != f && e != a) { d } else { e });
+    copy_to_vec(scaled, pos+row+channels, e);
+    copy_to_vec(scaled, pos+row+2*channels, if (b == f && b != d && f != h && e != i) || (h == f && d != h && b != f && e != c) { f } else { e });
+    copy_to_vec(scaled, pos+2*row, if d == h && d != b && h != f { d } else { e });
+    copy_to_vec(scaled, pos+2*row+channels, if (h == f && d != h && b != f && e != g) || (d == h && d != b && h != f && e != i) { h } else { e });
+    copy_to_vec(scaled, pos+2*row+2*channels, if h == f && d != h && b != f { f } else { e });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_keeps_gradient_colors_where_average_mixes_them() {
+        // A black to white gradient of two colors, its 2x2 blocks hold 3 and then 1 black pixels
+        let buf: [u8; 8] = [
+            0,0,   0,255,
+            0,255, 255,255
+        ];
+
+        assert_eq!(downscale(&buf, 4, 2, 1, 2), Ok((2,1,vec![0, 255])));
+        assert_eq!(downscale_average(&buf, 4, 2, 1, 2), Ok((2,1,vec![64, 191])));
+    }
+
+    #[test]
+    fn solid_block_upscales_to_a_uniform_3x_block() {
+        let buf = [5u8, 6].repeat(2*2);
+
+        assert_eq!(upscale3x(&buf, 2, 2, 2), Ok((6,6,[5u8, 6].repeat(6*6))));
+        assert_eq!(upscale3x(&[1u8], 1, 1, 1), Ok((3,3,vec![1; 9])));
+    }
+
+    #[test]
+    fn large_factor_preserves_a_solid_color() {
+        // Every 16x16 block holds 256 equal pixels, one more than a u8 counter can hold
+        let buf = [3u8, 4, 5].repeat(32*32);
+
+        assert_eq!(downscale(&buf, 32, 32, 3, 16), Ok((2,2,[3u8, 4, 5].repeat(4))));
+    }
+
+    #[test]
+    fn evenly_split_blocks_pick_the_first_color() {
+        for _ in 0..20 {
+            assert_eq!(downscale(&[1u8, 2, 2, 1], 2, 2, 1, 2), Ok((1,1,vec![1])));
+            assert_eq!(downscale(&[2u8, 1, 1, 2], 2, 2, 1, 2), Ok((1,1,vec![2])));
+        }
+    }
+
+    #[test]
+    fn tiny_images_upscale() {
+        assert_eq!(upscale(&[7u8, 8], 1, 1, 2), Ok((2,2,[7u8, 8].repeat(4))));
+        assert_eq!(upscale(&[1u8, 2, 3, 4], 2, 2, 1), Ok((4,4,vec![1,1,2,2, 1,1,2,2, 3,3,4,4, 3,3,4,4])));
+        assert_eq!(upscale(&[1u8, 2, 3, 4, 5], 1, 5, 1), Ok((2,10,vec![1,1, 1,1, 2,2, 2,2, 3,3, 3,3, 4,4, 4,4, 5,5, 5,5])));
+        assert_eq!(upscale(&[1u8, 2, 3, 4, 5], 5, 1, 1), Ok((10,2,[1,1,2,2,3,3,4,4,5,5].repeat(2))));
+    }
+
+    #[test]
+    fn premultiplied_average_has_no_dark_fringe() {
+        // Opaque white next to transparent black
+        let buf = [255u8,255,255,255, 0,0,0,0];
+
+        assert_eq!(downscale_average(&buf, 2, 1, 4, 2), Ok((1,1,vec![128,128,128,128])));
+        assert_eq!(downscale_average_premultiplied(&buf, 2, 1, 4, 2, AlphaChannel::Last), Ok((1,1,vec![255,255,255,128])));
+    }
+
+    #[test]
+    fn packed_and_generic_mode_agree() {
+        let colors: [[u8; 4]; 4] = [[255,0,0,255], [0,255,0,255], [0,0,255,128], [0,0,0,0]];
+        // A pseudo random 12x12 image, so the 3x3 blocks hold majorities, ties and all sorts of mixes
+        let buf: Vec<u8> = (0..12*12usize).flat_map(|index| colors[(index*7+index/5) % 4]).collect();
+
+        for color_key in [None, Some(&colors[3][..]), Some(&colors[0][..])] {
+            let generic = mode_blocks(&buf, 12, 12, 4, 3, ModeFilter::generic(color_key)).unwrap();
+            let packed = mode_blocks(&buf, 12, 12, 4, 3, ModeFilter::packed(color_key)).unwrap();
+
+            assert_eq!(packed, generic);
+        }
+    }
+
+    #[test]
+    fn scaling_by_3_repeats_each_pixel_in_a_3x3_block() {
+        let buf = [1u8, 2, 3, 4];
+        let (width, height, scaled) = scale_nearest(&buf, 2, 2, 1, 3).unwrap();
+
+        assert_eq!((width, height), (6,6));
+        for (index, value) in scaled.iter().enumerate() {
+            let (x, y) = (index%6, index/6);
+            assert_eq!(*value, buf[(y/3)*2+x/3], "pixel {},{}", x, y);
+        }
+    }
+
+    #[test]
+    fn resizing_to_uneven_dimensions_keeps_a_uniform_color() {
+        let color = [12u8, 34, 56, 200];
+        let buf = color.repeat(5*3);
+
+        // Neither 7/5 nor 2/3 nor 3/5 is a whole number
+        for (target_width, target_height) in [(7,2), (3,5)] {
+            for sampling in [Sampling::Nearest, Sampling::Bilinear] {
+                let resized = resize_to(&buf, 5, 3, 4, target_width, target_height, sampling).unwrap();
+                assert_eq!(resized, (target_width,target_height,color.repeat(target_width*target_height)), "{}x{}", target_width, target_height);
+            }
+        }
+    }
+
+    #[test]
+    fn upscale_does_not_leak_the_first_pixel() {
+        // No two neighbours are equal, so Scale2x must simply double every pixel
+        let mut buf: Vec<u8> = (0..3*3).flat_map(|index| [10+index as u8, 20, 30, 255]).collect();
+        buf[0..4].copy_from_slice(&[250, 250, 250, 250]);
+
+        let (width, height, scaled) = upscale(&buf, 3, 3, 4).unwrap();
+        for (index, pixel) in scaled.chunks(4).enumerate() {
+            let (x, y) = (index%width, index/width);
+            let source = ((y/2)*3+x/2)*4;
+            assert_eq!(pixel, &buf[source..source+4], "pixel {},{} of {}x{}", x, y, width, height);
+        }
+    }
+
+    #[test]
+    fn thumbnail_preserves_the_aspect_ratio() {
+        let buf: Vec<u8> = (0..100*50*3).map(|value| (value%256) as u8).collect();
+
+        let (width, height, thumb) = thumbnail(&buf, 100, 50, 3, 40, 40, None).unwrap();
+        assert_eq!((width, height, thumb.len()), (40, 20, 40*20*3));
+    }
+
+    #[test]
+    fn gaussian_downscale_of_a_checkerboard_is_gray() {
+        let buf: Vec<u8> = (0..32*32).map(|index| if (index%32 + index/32)%2 == 0 { 255 } else { 0 }).collect();
+        let (width, height, scaled) = downscale_with_filter(&buf, 32, 32, 1, 8, DownscaleFilter::Gaussian, AlphaChannel::Opaque).unwrap();
+
+        assert_eq!((width, height), (4,4));
+        assert!(scaled.iter().all(|value| value.abs_diff(128) <= 3), "{:?}", scaled);
+    }
+
+    #[test]
+    fn dithering_only_emits_colors_of_the_block() {
+        let colors: [[u8; 3]; 3] = [[255,0,0], [0,255,0], [0,0,255]];
+        let buf: Vec<u8> = (0..64*64usize).flat_map(|index| {
+            let (x, y) = (index%64, index/64);
+            colors[((x*7+y*3)/13 + (x*y)%3) % 3]
+        }).collect();
+
+        let (width, height, dithered) = downscale_with_filter(&buf, 64, 64, 3, 4, DownscaleFilter::DitheredMode, AlphaChannel::Opaque).unwrap();
+        for (index, pixel) in dithered.chunks(3).enumerate() {
+            let (block_x, block_y) = (index%width*4, index/width*4);
+            let found = (block_y..block_y+4).any(|y| (block_x..block_x+4).any(|x| &buf[(y*64+x)*3..(y*64+x)*3+3] == pixel));
+            assert!(found, "pixel {} of {}x{} is not in its block", index, width, height);
+        }
+    }
+
+    #[test]
+    fn zero_factor_is_rejected_by_every_downscale() {
+        let buf = [1u8; 16];
+
+        assert_eq!(downscale(&buf, 4, 4, 1, 0), Err(ParsingError::ZeroScale));
+        assert_eq!(downscale_keyed(&buf, 4, 4, 1, 0, Some(&[0])), Err(ParsingError::ZeroScale));
+        assert_eq!(downscale_average(&buf, 4, 4, 1, 0), Err(ParsingError::ZeroScale));
+        assert_eq!(downscale_average_premultiplied(&buf, 4, 4, 1, 0, AlphaChannel::Opaque), Err(ParsingError::ZeroScale));
+        assert_eq!(downscale_with_filter(&buf, 4, 4, 1, 0, DownscaleFilter::Average, AlphaChannel::Opaque), Err(ParsingError::ZeroScale));
+    }
+}
@@ -0,0 +1,74 @@
+use crate::blur::blur_values;
+use crate::{buffer_len, check_buffer, ParsingError, PositionedResult, StitchableType};
+
+/// Builds a drop shadow layer for an image out of its alpha channel (the last channel),
+/// which can then be stitched underneath the image with StitchingOrder::SourceOnTop
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - offset - How far the shadow is shifted from the image, positive => right/down
+/// - color - Color of the shadow, its alpha is the opacity of the darkest part of the shadow
+/// - blur - Radius of the box blur applied to the shadow, 0 gives a hard shadow
+///
+/// The shadow layer is grown to fit the offset and the blur,
+/// so it is returned along with the position the top left pixel of the image should be placed at in it
+pub fn drop_shadow<T>(buf: &[T], width: usize, height: usize, channels: usize, offset: (isize,isize), color: &[T], blur: usize) -> PositionedResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+    if color.len() != channels {
+        return Err(ParsingError::ChannelMismatch)
+    }
+
+    let overflow = || ParsingError::DimensionsTooLarge;
+    let margin = blur.checked_mul(2).ok_or_else(overflow)?;
+    let new_width = width.checked_add(offset.0.unsigned_abs()).and_then(|w| w.checked_add(margin)).ok_or_else(overflow)?;
+    let new_height = height.checked_add(offset.1.unsigned_abs()).and_then(|h| h.checked_add(margin)).ok_or_else(overflow)?;
+    buffer_len(new_width, new_height, channels)?;
+
+    let image_position = (blur + (-offset.0).max(0) as usize, blur + (-offset.1).max(0) as usize);
+    let shadow_position = (blur + offset.0.max(0) as usize, blur + offset.1.max(0) as usize);
+
+    let max = T::maxvalue().to_f64();
+    let alpha_index = channels-1;
+
+    let mut mask = vec![0.0; new_width*new_height];
+    for y in 0..height {
+        for x in 0..width {
+            mask[(shadow_position.1+y)*new_width+shadow_position.0+x] = buf[(y*width+x)*channels+alpha_index].to_f64()/max;
+        }
+    }
+
+    // The margins are at least as wide as the blur, so clamping at the edges of the layer only ever reads empty mask
+    if blur > 0 {
+        mask = blur_values(&mask, new_width, new_height, 1, blur)?;
+    }
+
+    let shadow_alpha = color[alpha_index].to_f64();
+    let mut result_buffer: Vec<T> = Vec::with_capacity(new_width*new_height*channels);
+
+    for coverage in mask {
+        result_buffer.extend_from_slice(&color[..alpha_index]);
+        result_buffer.push(T::from_f64(shadow_alpha*coverage));
+    }
+
+    Ok(((new_width,new_height,result_buffer),image_position))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blurred_shadow_spreads_evenly_into_the_margins() {
+        let ((width, height, shadow), position) = drop_shadow(&[255u8, 255, 255, 255], 1, 1, 4, (0,0), &[10, 20, 30, 255], 1).unwrap();
+
+        assert_eq!((width, height, position), (3, 3, (1,1)));
+        for pixel in shadow.chunks(4) {
+            assert_eq!(pixel, [10, 20, 30, 28]);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::warp::centered;
+use crate::{check_empty, content_bounds, crop, fast_rotate, tint, warp_affine, Image, ParsingError, Sampling, StitchableType};
+
+/// A pipeline of operations that is applied to an Image in one go
+///
+/// Consecutive rotations, scales, shears and flips are multiplied into a single matrix and applied in one warp_affine() pass,
+/// or one fast_rotate() pass if they add up to a rotation (see Transform::rotate()), so no intermediate image is made between them and pixels are only resampled once.
+/// The other operations each make one pass over the image, e.g. Transform::new().rotate(angle).scale(2.0).trim().apply(&image, empty)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transform<T> {
+    operations: Vec<Operation<T>>,
+    sampling: Sampling
+}
+
+/// A recorded step of a Transform, consecutive linear ones are already merged into one
+#[derive(Clone, Debug, PartialEq)]
+enum Operation<T> {
+    /// The linear part [a, b, d, e] of an affine matrix, see warp_affine()
+    Linear([f64; 4]),
+    Trim,
+    Tint(Vec<T>, f64)
+}
+
+impl<T> Default for Transform<T> {
+    fn default() -> Transform<T> {
+        Transform::new()
+    }
+}
+
+impl<T> Transform<T> {
+    /// Creates an empty Transform, applying it returns the image as is
+    pub fn new() -> Transform<T> {
+        Transform { operations: Vec::new(), sampling: Sampling::Nearest }
+    }
+
+    /// Sets how pixels are read by the rotations, scales, shears and flips, defaults to Sampling::Nearest
+    pub fn sampling(mut self, sampling: Sampling) -> Transform<T> {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Rotates the image
+    ///
+    /// # Arguments
+    ///
+    /// - angle - The angle of rotation (in radians),
+    ///   - positive => Anticlockwise,
+    ///   - negative => Clockwise
+    ///
+    /// With Sampling::Nearest, linear steps that merge into nothing but a rotation are applied with fast_rotate(), so the result is the same.
+    /// Anything else, e.g. a rotation merged with a scale, goes through warp_affine(), which differs from fast_rotate() in a few ways:
+    /// positions are snapped with floor(x+0.5) so ties go right/down instead of away from the center,
+    /// the canvas is sized from the bounding box of the transformed corners and quarter turns are sampled rather than copied exactly
+    pub fn rotate(self, angle: f64) -> Transform<T> {
+        let (sin, cos) = angle.sin_cos();
+        self.linear([cos, sin, -sin, cos])
+    }
+
+    /// Scales the image by a factor, which may be fractional
+    pub fn scale(self, factor: f64) -> Transform<T> {
+        self.linear([factor, 0.0, 0.0, factor])
+    }
+
+    /// Slants the image, see shear()
+    pub fn shear(self, shear_x: f64, shear_y: f64) -> Transform<T> {
+        self.linear([1.0, shear_x, shear_y, 1.0])
+    }
+
+    /// Mirrors the image left to right
+    pub fn flip_horizontal(self) -> Transform<T> {
+        self.linear([-1.0, 0.0, 0.0, 1.0])
+    }
+
+    /// Mirrors the image top to bottom
+    pub fn flip_vertical(self) -> Transform<T> {
+        self.linear([1.0, 0.0, 0.0, -1.0])
+    }
+
+    /// Crops away the empty margins of the image, see trim().
+    /// An image that is entirely empty is left as is
+    pub fn trim(mut self) -> Transform<T> {
+        self.operations.push(Operation::Trim);
+        self
+    }
+
+    /// Tints the image towards a color, see tint()
+    pub fn tint(mut self, color: &[T], strength: f64) -> Transform<T>
+    where T: Clone
+    {
+        self.operations.push(Operation::Tint(color.to_vec(), strength));
+        self
+    }
+
+    /// Records a linear transform, merging it into the previous one if that was linear too
+    fn linear(mut self, [a, b, d, e]: [f64; 4]) -> Transform<T> {
+        if let Some(Operation::Linear(previous)) = self.operations.last_mut() {
+            // This one is applied after the previous one, so it goes on the left
+            let [pa, pb, pd, pe] = *previous;
+            *previous = [a*pa + b*pd, a*pb + b*pe, d*pa + e*pd, d*pb + e*pe];
+        } else {
+            self.operations.push(Operation::Linear([a, b, d, e]));
+        }
+        self
+    }
+}
+
+impl<T> Transform<T>
+where T: StitchableType + std::fmt::Debug, [T]: Eq
+{
+    /// Applies the operations to an image in the order they were added
+    ///
+    /// # Arguments
+    ///
+    /// - image - The image
+    /// - empty - Empty space is filled with this value, and trimming treats pixels equal to it as empty
+    pub fn apply(&self, image: &Image<T>, empty: &[T]) -> Result<Image<T>, ParsingError> {
+        check_empty(empty, image.channels())?;
+
+        let channels = image.channels();
+        let mut current = image.clone();
+
+        for operation in self.operations.iter() {
+            let result = match operation {
+                Operation::Linear(matrix) if *matrix == [1.0, 0.0, 0.0, 1.0] => continue,
+                Operation::Linear(matrix) if self.sampling == Sampling::Nearest && is_rotation(matrix) => {
+                    fast_rotate(current.data(), empty, channels, current.width(), current.height(), matrix[1].atan2(matrix[0]))
+                },
+                Operation::Linear([a, b, d, e]) => warp_affine(current.data(), current.width(), current.height(), channels, empty, centered([*a, *b, 0.0, *d, *e, 0.0], current.width(), current.height()), self.sampling),
+                Operation::Trim => match content_bounds(current.data(), current.width(), current.height(), channels, empty)? {
+                    Some((min_x, min_y, max_x, max_y)) => crop(current.data(), current.width(), current.height(), channels, (min_x, min_y), (max_x-min_x+1, max_y-min_y+1)),
+                    None => continue
+                },
+                Operation::Tint(color, strength) => tint(current.data(), current.width(), current.height(), channels, color, *strength)
+            };
+
+            current = Image::from_result(result, channels)?;
+        }
+
+        Ok(current)
+    }
+}
+
+/// Whether the linear part [a, b, d, e] of an affine matrix is (up to floating point error) a pure rotation,
+/// i.e. [cos, sin, -sin, cos] for some angle
+fn is_rotation([a, b, d, e]: &[f64; 4]) -> bool {
+    const TOLERANCE: f64 = 1e-12;
+
+    (a-e).abs() < TOLERANCE && (b+d).abs() < TOLERANCE && (a*a + b*b - 1.0).abs() < TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chained_rotate_and_scale_match_separate_steps() {
+        let buf: Vec<u8> = (0..12*7*4).map(|index| if index%4 == 3 { 255 } else { (index/4*37%256) as u8 }).collect();
+        let image = Image::new(buf.clone(), 12, 7, 4).unwrap();
+        let empty = [0u8; 4];
+        let angle = std::f64::consts::FRAC_PI_2;
+
+        let (width, height, rotated) = fast_rotate(&buf, &empty, 4, 12, 7, angle).unwrap();
+        let expected = crate::scale_nearest(&rotated, width, height, 4, 2).unwrap();
+
+        assert_eq!(Transform::new().rotate(angle).scale(2.0).apply(&image, &empty).map(Image::into_parts), Ok(expected));
+    }
+
+    #[test]
+    fn rotations_match_fast_rotate() {
+        let image = Image::new((0..5*3*2).map(|value| value as u8).collect(), 5, 3, 2).unwrap();
+        let empty = [0u8, 0];
+
+        for angle in [0.3, std::f64::consts::FRAC_PI_2, std::f64::consts::PI, -2.0] {
+            let expected = Image::from_result(fast_rotate(image.data(), &empty, 2, 5, 3, angle), 2).unwrap();
+            assert_eq!(Transform::new().rotate(angle).apply(&image, &empty), Ok(expected.clone()), "angle {}", angle);
+            assert_eq!(Transform::new().rotate(angle/2.0).rotate(angle/2.0).apply(&image, &empty).map(|rotated| rotated.dimensions()), Ok(expected.dimensions()));
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{buffer_len, check_buffer, check_empty, filled_canvas, ParsingError, StitchableType, StitchingResult};
+
+/// Determines how a pixel is read from the source image when it maps between pixel centers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    /// Copies the closest source pixel, wont introduce any new colors
+    Nearest,
+    /// Interpolates between the 4 closest source pixels, smoother but introduces new colors
+    Bilinear
+}
+
+/// Applies an affine transform (any combination of rotation, scaling, shearing and translation) to an image
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel, must be at least 1
+/// - empty - Empty space will be filled with this value
+/// - matrix - The transform [a, b, c, d, e, f], which maps the point (x, y) to (a\*x + b\*y + c, d\*x + e\*y + f).
+///   y points down, so [cos, sin, 0, -sin, cos, 0] is an anticlockwise rotation about the top left corner.
+///   Points are continuous, so pixel (x, y) covers x..x+1 and y..y+1
+/// - sampling - How pixels are read from the source image
+///
+/// The result covers the bounding box of the transformed corners, its top left pixel lies at the smallest transformed x and y rounded to whole pixels,
+/// the same as warp_perspective(). So c and f decide where that pixel lies and their fractional parts shift the pixels that are sampled.
+/// Returns ParsingError::SingularMatrix if the matrix cannot be inverted
+pub fn warp_affine<T>(buf: &[T], width: usize, height: usize, channels: usize, empty: &[T], matrix: [f64; 6], sampling: Sampling) -> StitchingResult<T>
+where T: StitchableType
+{
+    match sampling {
+        Sampling::Nearest => warp_with(buf, width, height, channels, empty, matrix, sample_nearest),
+        Sampling::Bilinear => warp_with(buf, width, height, channels, empty, matrix, sample_bilinear)
+    }
+}
+
+/// Applies a perspective transform (homography) to an image, e.g. for faux 3D card flips or laying a sprite onto a ground plane
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel, must be at least 1
+/// - empty - Empty space will be filled with this value
+/// - homography - The row major 3x3 matrix [a, b, c, d, e, f, g, h, i], which maps the point (x, y) to
+///   ((a\*x + b\*y + c)/w, (d\*x + e\*y + f)/w) where w = g\*x + h\*y + i. Points are continuous, so pixel (x, y) covers x..x+1 and y..y+1
+/// - sampling - How pixels are read from the source image
+///
+/// The result covers the bounding box of the transformed corners, its top left pixel lies at the smallest transformed x and y.
+/// Each dimension is capped at MAX_WARP_DIMENSION, cropping the right and bottom of extreme projections.
+/// Returns ParsingError::SingularMatrix if the matrix cannot be inverted or it sends part of the image to or past infinity (w <= 0)
+pub fn warp_perspective<T>(buf: &[T], width: usize, height: usize, channels: usize, empty: &[T], homography: [f64; 9], sampling: Sampling) -> StitchingResult<T>
+where T: StitchableType
+{
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+
+    let inverse = invert_3x3(homography).ok_or(ParsingError::SingularMatrix)?;
+    let project = |matrix: &[f64; 9], x: f64, y: f64| {
+        let w = matrix[6]*x + matrix[7]*y + matrix[8];
+        (w, (matrix[0]*x + matrix[1]*y + matrix[2])/w, (matrix[3]*x + matrix[4]*y + matrix[5])/w)
+    };
+
+    let widthf = width as f64;
+    let heightf = height as f64;
+
+    // w only changes sign along a line, so if it is positive at every corner it is positive over the whole image
+    let mut min = (f64::MAX, f64::MAX);
+    let mut max = (f64::MIN, f64::MIN);
+    for (x, y) in [(0.0, 0.0), (widthf, 0.0), (0.0, heightf), (widthf, heightf)] {
+        let (w, x, y) = project(&homography, x, y);
+        if w <= 0.0 || !w.is_finite() || !x.is_finite() || !y.is_finite() {
+            return Err(ParsingError::SingularMatrix)
+        }
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+
+    let origin = (min.0.round(), min.1.round());
+    let new_width = ((max.0.round() - origin.0).max(1.0).min(MAX_WARP_DIMENSION as f64)) as usize;
+    let new_height = ((max.1.round() - origin.1).max(1.0).min(MAX_WARP_DIMENSION as f64)) as usize;
+    buffer_len(new_width, new_height, channels)?;
+
+    debug!("Warping {}x{} by {:?} gives dimensions {}x{}",width,height,homography,new_width,new_height);
+
+    let sample = match sampling {
+        Sampling::Nearest => sample_nearest,
+        Sampling::Bilinear => sample_bilinear
+    };
+    let mut result_buffer: Vec<T> = filled_canvas(empty, new_width*new_height);
+
+    for (index, pixel) in result_buffer.chunks_mut(channels).enumerate() {
+        let (w, x, y) = project(&inverse, (index%new_width) as f64+0.5+origin.0, (index/new_width) as f64+0.5+origin.1);
+        if w <= 0.0 || w.is_nan() {
+            continue
+        }
+
+        let source_x = x-0.5;
+        let source_y = y-0.5;
+
+        // Same tie breaking as warp_with()
+        let nearest_x = (source_x+0.5).floor();
+        let nearest_y = (source_y+0.5).floor();
+        if 0.0 <= nearest_x && nearest_x < widthf && 0.0 <= nearest_y && nearest_y < heightf {
+            sample(buf, width, height, channels, source_x, source_y, pixel);
+        }
+    }
+
+    Ok((new_width,new_height,result_buffer))
+}
+
+/// The largest width or height warp_perspective() will produce, projections close to the horizon can otherwise get arbitrarily large
+pub const MAX_WARP_DIMENSION: usize = 16384;
+
+/// Inverts a row major 3x3 matrix, None if it is singular or contains values that are not finite
+fn invert_3x3(m: [f64; 9]) -> Option<[f64; 9]> {
+    let cofactors = [
+        m[4]*m[8] - m[5]*m[7], m[2]*m[7] - m[1]*m[8], m[1]*m[5] - m[2]*m[4],
+        m[5]*m[6] - m[3]*m[8], m[0]*m[8] - m[2]*m[6], m[2]*m[3] - m[0]*m[5],
+        m[3]*m[7] - m[4]*m[6], m[1]*m[6] - m[0]*m[7], m[0]*m[4] - m[1]*m[3]
+    ];
+    let determinant = m[0]*cofactors[0] + m[1]*cofactors[3] + m[2]*cofactors[6];
+
+    if !m.iter().all(|value| value.is_finite()) || determinant == 0.0 || !determinant.is_finite() {
+        return None
+    }
+
+    Some(cofactors.map(|value| value/determinant))
+}
+
+/// Slants an image, growing the canvas to fit.
+/// Uses nearest neighbour sampling like fast_rotate(), so no new colors are introduced
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel, must be at least 1
+/// - empty - Empty space will be filled with this value
+/// - shear_x - How far each row moves right per row down, 1.0 slants vertical edges by 45 degrees
+/// - shear_y - How far each column moves down per column right
+///
+/// Returns ParsingError::SingularMatrix if shear_x\*shear_y is 1, as that squashes the image onto a line
+pub fn shear<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, empty: &[T], shear_x: f64, shear_y: f64) -> StitchingResult<T> {
+    warp_with(buf, width, height, channels, empty, centered([1.0, shear_x, 0.0, shear_y, 1.0, 0.0], width, height), sample_nearest)
+}
+
+/// Replaces the translation of matrix with the one that puts the center of the image at the center of its bounding box,
+/// with the box rounded to whole pixels. Then the result of warp_affine() is placed the same way fast_rotate() places a rotation
+pub(crate) fn centered(matrix: [f64; 6], width: usize, height: usize) -> [f64; 6] {
+    let [a, b, _, d, e, _] = matrix;
+    let widthf = width as f64;
+    let heightf = height as f64;
+
+    let corners = [(0.0, 0.0), (widthf, 0.0), (0.0, heightf), (widthf, heightf)].map(|(x, y)| (a*x + b*y, d*x + e*y));
+    let extent = |axis: fn(&(f64,f64)) -> f64| {
+        let values = corners.iter().map(axis);
+        values.clone().fold(f64::MIN, f64::max) - values.fold(f64::MAX, f64::min)
+    };
+    let center = (a*widthf/2.0 + b*heightf/2.0, d*widthf/2.0 + e*heightf/2.0);
+
+    [a, b, extent(|corner| corner.0).round().max(1.0)/2.0 - center.0, d, e, extent(|corner| corner.1).round().max(1.0)/2.0 - center.1]
+}
+
+/// Reads the source pixel at a position into the last argument, see sample_nearest() and sample_bilinear()
+type Sampler<T> = fn(&[T], usize, usize, usize, f64, f64, &mut [T]);
+
+/// Warps an image by the given matrix (see warp_affine()) by mapping each pixel of the canvas back onto the source image,
+/// sample is called with the source position of every canvas pixel whose nearest source pixel lies within the image
+fn warp_with<T: Clone>(buf: &[T], width: usize, height: usize, channels: usize, empty: &[T], matrix: [f64; 6], sample: Sampler<T>) -> StitchingResult<T> {
+    check_buffer(buf, channels, width, height)?;
+    check_empty(empty, channels)?;
+
+    let [a, b, c, d, e, f] = matrix;
+    let determinant = a*e - b*d;
+    if !matrix.iter().all(|value| value.is_finite()) || determinant == 0.0 || !determinant.is_finite() {
+        return Err(ParsingError::SingularMatrix)
+    }
+
+    let widthf = width as f64;
+    let heightf = height as f64;
+
+    let mut min = (f64::MAX, f64::MAX);
+    let mut max = (f64::MIN, f64::MIN);
+    for (x, y) in [(0.0, 0.0), (widthf, 0.0), (0.0, heightf), (widthf, heightf)] {
+        let (x, y) = (a*x + b*y + c, d*x + e*y + f);
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+
+    let origin = (min.0.round(), min.1.round());
+    let new_widthf = (max.0.round() - origin.0).max(1.0);
+    let new_heightf = (max.1.round() - origin.1).max(1.0);
+    if !new_widthf.is_finite() || !new_heightf.is_finite() || new_widthf > usize::MAX as f64 || new_heightf > usize::MAX as f64 {
+        return Err(ParsingError::DimensionsTooLarge)
+    }
+    let (new_width, new_height) = (new_widthf as usize, new_heightf as usize);
+    buffer_len(new_width, new_height, channels)?;
+
+    debug!("Warping {}x{} by {:?} gives dimensions {}x{}",width,height,matrix,new_width,new_height);
+
+    let inverse = [e/determinant, -b/determinant, -d/determinant, a/determinant];
+    let mut result_buffer: Vec<T> = filled_canvas(empty, new_width*new_height);
+
+    for (index, pixel) in result_buffer.chunks_mut(channels).enumerate() {
+        let pos: [f64; 2] = [(index%new_width) as f64+0.5+origin.0-c, (index/new_width) as f64+0.5+origin.1-f];
+
+        let source_x = (pos[0]*inverse[0] + pos[1]*inverse[1])-0.5;
+        let source_y = (pos[0]*inverse[2] + pos[1]*inverse[3])-0.5;
+
+        // Ties are always broken towards the right/bottom, so exact shears dont drop pixels at the -0.5 edge
+        let nearest_x = (source_x+0.5).floor();
+        let nearest_y = (source_y+0.5).floor();
+        if 0.0 <= nearest_x && nearest_x < widthf && 0.0 <= nearest_y && nearest_y < heightf {
+            sample(buf, width, height, channels, source_x, source_y, pixel);
+        }
+    }
+
+    Ok((new_width,new_height,result_buffer))
+}
+
+/// Copies the source pixel closest to (x, y)
+pub(crate) fn sample_nearest<T: Clone>(buf: &[T], width: usize, _height: usize, channels: usize, x: f64, y: f64, pixel: &mut [T]) {
+    let index = ((y+0.5).floor() as usize*width+(x+0.5).floor() as usize)*channels;
+    pixel.clone_from_slice(&buf[index..index+channels]);
+}
+
+/// Interpolates the pixel at (x, y) from its 4 closest neighbours, neighbours past the edge are clamped onto it
+pub(crate) fn sample_bilinear<T: StitchableType>(buf: &[T], width: usize, height: usize, channels: usize, x: f64, y: f64, pixel: &mut [T]) {
+    let clamp = |value: f64, size: usize| (value.max(0.0) as usize).min(size-1);
+
+    let x0 = clamp(x.floor(), width);
+    let y0 = clamp(y.floor(), height);
+    let x1 = clamp(x.floor()+1.0, width);
+    let y1 = clamp(y.floor()+1.0, height);
+    let fx = x - x.floor();
+    let fy = y - y.floor();
+
+    let at = |x: usize, y: usize, channel: usize| buf[(y*width+x)*channels+channel].to_f64();
+
+    for (channel, value) in pixel.iter_mut().enumerate() {
+        let top = at(x0, y0, channel)*(1.0-fx) + at(x1, y0, channel)*fx;
+        let bottom = at(x0, y1, channel)*(1.0-fx) + at(x1, y1, channel)*fx;
+        *value = T::from_f64(top*(1.0-fy) + bottom*fy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fast_rotate;
+
+    #[test]
+    fn pure_rotation_matches_fast_rotate() {
+        let buf: Vec<u8> = (0..13*9*4).map(|value| (value*31%251) as u8).collect();
+
+        for angle in [0.3f64, 1.0, 2.5, -0.7, -2.9, 0.05] {
+            // Once centered, the rotation is placed the way fast_rotate() places it
+            let matrix = centered([angle.cos(), angle.sin(), 5.0, -angle.sin(), angle.cos(), -3.0], 13, 9);
+
+            assert_eq!(warp_affine(&buf, 13, 9, 4, &[0,0,0,0], matrix, Sampling::Nearest), fast_rotate(&buf, &[0,0,0,0], 4, 13, 9, angle), "angle {}", angle);
+        }
+    }
+
+    #[test]
+    fn translation_moves_the_sampled_pixels() {
+        let buf = [0u8, 100, 200];
+
+        assert_eq!(warp_affine(&buf, 3, 1, 1, &[0], [1.0, 0.0, 0.0, 0.0, 1.0, 0.0], Sampling::Bilinear), Ok((3,1,vec![0, 100, 200])));
+        // Whole pixels only move the top left pixel, which still lies at the smallest transformed x
+        assert_eq!(warp_affine(&buf, 3, 1, 1, &[0], [1.0, 0.0, 7.0, 0.0, 1.0, -2.0], Sampling::Bilinear), Ok((3,1,vec![0, 100, 200])));
+        // Half a pixel rounds the top left pixel up to x = 1, so each pixel is read halfway between two source pixels
+        // and the last one is read at the right edge, which ties towards the outside
+        assert_eq!(warp_affine(&buf, 3, 1, 1, &[0], [1.0, 0.0, 0.5, 0.0, 1.0, 0.0], Sampling::Bilinear), Ok((3,1,vec![50, 150, 0])));
+    }
+
+    #[test]
+    fn zero_shear_is_a_no_op_and_45_degrees_doubles_the_width() {
+        let buf: Vec<u8> = (0..5*4*2).map(|value| value as u8 + 1).collect();
+        assert_eq!(shear(&buf, 5, 4, 2, &[0,0], 0.0, 0.0), Ok((5,4,buf)));
+
+        let square: Vec<u8> = (0..6*6).map(|value| value as u8 + 1).collect();
+        let (width, height, sheared) = shear(&square, 6, 6, 1, &[0], 1.0, 0.0).unwrap();
+        assert_eq!((width, height), (12,6));
+        assert_eq!(sheared.iter().filter(|value| **value != 0).count(), 6*6);
+    }
+
+    #[test]
+    fn homographies_map_quads_onto_quads() {
+        let buf: Vec<u8> = (0..6*4*2).map(|value| value as u8).collect();
+        let identity = [1.0,0.0,0.0, 0.0,1.0,0.0, 0.0,0.0,1.0];
+        assert_eq!(warp_perspective(&buf, 6, 4, 2, &[0,0], identity, Sampling::Nearest), Ok((6,4,buf.clone())));
+        assert_eq!(warp_perspective(&buf, 6, 4, 2, &[0,0], identity, Sampling::Bilinear), Ok((6,4,buf)));
+
+        // A keystone that narrows the bottom, with a 2x2 block of a distinct value in each corner
+        let mut image = vec![0u8; 20*20];
+        for d in [0, 1] {
+            for e in [0, 1] {
+                image[d*20+e] = 1;
+                image[d*20+19-e] = 2;
+                image[(19-d)*20+e] = 3;
+                image[(19-d)*20+19-e] = 4;
+            }
+        }
+        let k = 0.05;
+        let (width, height, warped) = warp_perspective(&image, 20, 20, 1, &[0], [1.0,0.0,0.0, 0.0,1.0,0.0, 0.0,k,1.0], Sampling::Nearest).unwrap();
+
+        // Where the homography sends a point, one pixel in from each corner
+        let at = |x: f64, y: f64| {
+            let w = k*y + 1.0;
+            warped[((y/w) as usize).min(height-1)*width + ((x/w) as usize).min(width-1)]
+        };
+        assert_eq!([at(1.0, 1.0), at(19.0, 1.0), at(1.0, 19.0), at(19.0, 19.0)], [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,83 @@
+//! Bindings for running in the browser through wasm-bindgen, e.g. for live sprite previews
+//!
+//! Images are passed in as 8 bit buffers with their dimensions, and every function returns a packed image:
+//! the width and height as little endian u32s followed by the image buffer.
+//! Errors are thrown as JS exceptions carrying the message of the ParsingError
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Appendage, Joint, ParsingError, StitchingOrder, StitchingQuality, StitchingResult};
+
+/// Rotates an image, see fast_rotate() and fancy_rotate(), empty space is filled with zeroes
+///
+/// # Arguments
+///
+/// - buf - The image
+/// - width, height - Dimensions of image
+/// - channels - No. of channels per pixel
+/// - angle - The angle of rotation (in radians), positive => Anticlockwise
+/// - fancy - Use fancy_rotate() instead of fast_rotate()
+#[wasm_bindgen(js_name = rotate)]
+pub fn rotate_packed(buf: &[u8], width: u32, height: u32, channels: u32, angle: f64, fancy: bool) -> Result<Vec<u8>, JsError> {
+    let (width, height, channels) = (to_usize(width)?, to_usize(height)?, to_usize(channels)?);
+    let empty = vec![0; channels];
+
+    let result = if fancy {
+        crate::fancy_rotate(buf, &empty, channels, width, height, angle)
+    } else {
+        crate::fast_rotate(buf, &empty, channels, width, height, angle)
+    };
+
+    pack(result)
+}
+
+/// Stitches an appendage onto an image, see stitch_appendage(), empty space is filled with zeroes
+///
+/// # Arguments
+///
+/// - src, src_width, src_height - The source image and its dimensions
+/// - src_anchor_x, src_anchor_y, src_angle - The point of stitching on the source and desired angle of the stitched appendage
+/// - appendage, appendage_width, appendage_height - The appendage image and its dimensions
+/// - appendage_anchor_x, appendage_anchor_y, appendage_angle - The point of stitching on the appendage and its current angle in image
+/// - channels - No. of channels per pixel of both images
+/// - appendage_on_top - Keep the appendage on top instead of the source
+/// - fancy - Rotate the appendage with fancy_rotate() instead of fast_rotate()
+#[wasm_bindgen(js_name = stitch)]
+// JS has no tuples, so every dimension and anchor is passed as a number of its own
+#[allow(clippy::too_many_arguments)]
+pub fn stitch_packed(src: &[u8], src_width: u32, src_height: u32, src_anchor_x: u32, src_anchor_y: u32, src_angle: f64, appendage: &[u8], appendage_width: u32, appendage_height: u32, appendage_anchor_x: u32, appendage_anchor_y: u32, appendage_angle: f64, channels: u32, appendage_on_top: bool, fancy: bool) -> Result<Vec<u8>, JsError> {
+    let channels = to_usize(channels)?;
+    let empty = vec![0; channels];
+
+    let top = if appendage_on_top { StitchingOrder::AppendageOnTop } else { StitchingOrder::SourceOnTop };
+    let quality = if fancy { StitchingQuality::Fancy } else { StitchingQuality::Fast };
+
+    let joint = Joint::new((to_usize(src_anchor_x)?, to_usize(src_anchor_y)?), src_angle, (to_usize(appendage_anchor_x)?, to_usize(appendage_anchor_y)?), appendage_angle, top);
+    let appendage = Appendage { buf: appendage, dimensions: (to_usize(appendage_width)?, to_usize(appendage_height)?), joint };
+
+    let result = crate::stitch_appendage(src, (to_usize(src_width)?, to_usize(src_height)?), &appendage, &empty, channels, quality);
+
+    pack(result)
+}
+
+/// Packs a result into the width, height and buffer layout described in the module docs
+fn pack(result: StitchingResult<u8>) -> Result<Vec<u8>, JsError> {
+    let (width, height, buf) = result.map_err(to_js)?;
+    let width = u32::try_from(width).map_err(|_| to_js(ParsingError::DimensionsTooLarge))?;
+    let height = u32::try_from(height).map_err(|_| to_js(ParsingError::DimensionsTooLarge))?;
+
+    let mut packed = Vec::with_capacity(8+buf.len());
+    packed.extend_from_slice(&width.to_le_bytes());
+    packed.extend_from_slice(&height.to_le_bytes());
+    packed.extend_from_slice(&buf);
+
+    Ok(packed)
+}
+
+fn to_usize(value: u32) -> Result<usize, JsError> {
+    usize::try_from(value).map_err(|_| to_js(ParsingError::DimensionsTooLarge))
+}
+
+fn to_js(error: ParsingError) -> JsError {
+    JsError::new(&error.to_string())
+}
@@ -0,0 +1,37 @@
+use acas::stitch;
+use image::{io::Reader, RgbaImage, ImageResult};
+
+const SHADOW_OFFSET: (isize, isize) = (3,3);
+const SHADOW_COLOR: [u8; 4] = [0,0,0,160];
+const SHADOW_BLUR: usize = 1;
+
+fn main() {
+    let sword = Reader::open("examples/stitching/assets/sword.png").unwrap().decode().unwrap().into_rgba8();
+    let (width, height) = sword.dimensions();
+    let sword = stitch::Image::from_u32_dimensions(sword.into_vec(), (width, height), 4).unwrap();
+
+    let (shadow, sword_position) = stitch::drop_shadow(
+        sword.data(),
+        sword.width(),
+        sword.height(),
+        sword.channels(),
+        SHADOW_OFFSET, // how far the shadow falls from the sword
+        &SHADOW_COLOR, // color and opacity of the shadow
+        SHADOW_BLUR // radius of the blur, 0 for a hard shadow
+    ).unwrap();
+    let shadow = stitch::Image::new(shadow.2, shadow.0, shadow.1, 4).unwrap();
+
+    // Draw the sword over its shadow, the top left of the sword goes where drop_shadow() says
+    let joint = stitch::Joint::new(sword_position, 0.0, (0,0), 0.0, stitch::StitchingOrder::AppendageOnTop);
+    let output = shadow.stitch(&sword, joint, &[0,0,0,0], stitch::StitchingQuality::Fast).unwrap();
+
+    save_image(output).unwrap();
+}
+
+fn save_image(image: stitch::Image<u8>) -> ImageResult<()> {
+    let (width, height) = image.dimensions_u32().unwrap();
+    let output_image = RgbaImage::from_vec(width,height,image.into_data()).unwrap();
+    output_image.save_with_format("examples/shadow/output.png", image::ImageFormat::Png)?;
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use acas::stitch;
+use image::io::Reader;
+use std::f64::consts;
+use std::fs::File;
+
+const FRAMES: usize = 24;
+const FRAME_DELAY_MS: u32 = 40;
+
+fn main() {
+    let spear = Reader::open("benches/input.png").unwrap().decode().unwrap().into_rgba8();
+    let (width, height) = spear.dimensions();
+    let spear = spear.into_vec();
+
+    // One full turn, the frames come out in different sizes as the spear turns
+    let angles: Vec<f64> = (0..FRAMES).map(|frame| frame as f64*consts::TAU/FRAMES as f64).collect();
+    let frames = stitch::rotate_sequence(&spear, &[0,0,0,0], 4, width as usize, height as usize, &angles).unwrap();
+
+    let frames: Vec<stitch::Image<u8>> = frames.into_iter()
+        .map(|(width, height, data)| stitch::Image::new(data, width, height, 4).unwrap())
+        .collect();
+
+    // encode_gif() pads every frame to the size of the largest one so the spear spins in place
+    let output = File::create("examples/spin/output.gif").unwrap();
+    stitch::encode_gif(&frames, &[0,0,0,0], FRAME_DELAY_MS, output).unwrap();
+}
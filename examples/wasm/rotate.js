@@ -0,0 +1,35 @@
+// Rotates the RGBA pixels of a canvas with acas-stitch running in the browser
+//
+// Build the bindings first with
+//   wasm-pack build crates/acas-stitch --target web -- --features wasm
+// and serve this file next to the generated pkg directory
+
+import init, { rotate } from "./pkg/acas_stitch.js";
+
+// Unpacks the result of the bindings: width and height as little endian u32s, followed by the pixels
+function unpack(packed) {
+    const header = new DataView(packed.buffer, packed.byteOffset, 8);
+    const width = header.getUint32(0, true);
+    const height = header.getUint32(4, true);
+
+    return new ImageData(new Uint8ClampedArray(packed.slice(8)), width, height);
+}
+
+export async function rotateCanvas(canvas, angle) {
+    await init();
+
+    const context = canvas.getContext("2d");
+    const pixels = context.getImageData(0, 0, canvas.width, canvas.height);
+
+    try {
+        // 4 channels (RGBA), fancy rotation to keep pixel art crisp
+        const rotated = unpack(rotate(new Uint8Array(pixels.data.buffer), canvas.width, canvas.height, 4, angle, true));
+
+        canvas.width = rotated.width;
+        canvas.height = rotated.height;
+        context.putImageData(rotated, 0, 0);
+    } catch (error) {
+        // Errors from acas-stitch arrive as exceptions with the message of the ParsingError
+        console.error(`Could not rotate: ${error.message}`);
+    }
+}